@@ -7,6 +7,6 @@ pub fn main() {
     log_setup();
     let mut cmd = Command::new("cargo");
     cmd.arg("-v");
-    let op = cmd.output().and_then(|o| Ok(o.status.success()));
+    let op = cmd.output().map(|o| o.status.success());
     info!("cmd : {}: {op:?}", &cmd.to_string_pretty())
 }
@@ -55,7 +55,7 @@ fn is_simple_value(field: &Type) -> bool {
         "f32", "f64", "bool", "char", "()",
     ];
     let type_str = quote!(#field).to_string();
-    return simple_types.contains(&type_str.as_str());
+    simple_types.contains(&type_str.as_str())
 }
 
 fn is_visibility_less_than_struct(
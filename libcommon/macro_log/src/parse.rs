@@ -78,19 +78,19 @@ fn expr_to_ident(expr: syn::Expr) -> syn::Result<Expr> {
 
 // 辅助函数：将表达式转换为LitStr
 fn expr_to_lit_str(expr: &syn::Expr) -> syn::Result<LitStr> {
-    match expr {
-        syn::Expr::Lit(expr_lit) => match &expr_lit.lit {
-            syn::Lit::Str(lit_str) => return Ok(lit_str.clone()),
-            _ => {}
-        },
-        _ => {}
+    if let syn::Expr::Lit(syn::ExprLit {
+        lit: syn::Lit::Str(lit_str),
+        ..
+    }) = expr
+    {
+        return Ok(lit_str.clone());
     }
-    return Err(syn::Error::new(expr.span(), "dir must be a string literal"));
+    Err(syn::Error::new(expr.span(), "dir must be a string literal"))
 }
 
 fn is_level_str(str: &str) -> (bool, Vec<&str>) {
     let levels = vec!["trace", "debug", "info", "warn", "error", "record"];
-    return (levels.contains(&str), levels);
+    (levels.contains(&str), levels)
 }
 
 // 辅助函数：将表达式转换为level字符串
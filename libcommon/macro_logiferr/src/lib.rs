@@ -5,6 +5,9 @@ use quote::quote;
 /// 如果函数返回错误，则打印错误日志
 ///
 /// 需要函数返回[Result]类型
+///
+/// 日志为错误的`{:?}`输出：`libcommon::prelude::Err`输出`libcommon::prelude::ErrReportExt::report`，
+/// 包括错误链、每层的类型、字段和创建位置，以及调用栈(设置了`RUST_BACKTRACE`时)；其它错误类型输出其[Debug]
 #[proc_macro_attribute]
 pub fn logiferr(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
@@ -23,7 +26,7 @@ pub fn logiferr(_attr: TokenStream, item: TokenStream) -> TokenStream {
         #fn_vis #fn_sig{
             let result:Result<_> = (||#fn_block)();
             if let Err(e) = &result {
-                error!("fn({}) failed: {}", stringify!(#fn_name), e);
+                error!("fn({}) failed: {:?}", stringify!(#fn_name), e);
             }
             result
         }
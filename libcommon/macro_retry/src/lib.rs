@@ -0,0 +1,89 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{LitInt, LitStr};
+
+///
+/// 函数返回错误时按策略重试
+///
+/// 需要函数返回`libcommon::prelude::Result`类型，使用`libcommon::retry::retry`执行，
+/// 异步函数使用`libcommon::retry::retry_async`，需要启用`tokio`特性
+///
+/// 参数:
+/// - `times`: 最多执行的次数，默认3
+/// - `backoff`: `"fixed"`、`"exp"`或`"jitter"`，默认`"fixed"`
+/// - `delay`: 首次重试前等待的毫秒数，默认100
+///
+/// 函数体每次重试都会重新执行，因此不能移动参数的所有权；异步函数的参数需要实现[Copy]
+///
+/// # example
+/// ```ignore
+/// #[retry(times = 3, backoff = "exp")]
+/// fn fetch(url: &str) -> Result<String> {
+///     ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn retry(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut times = 3u32;
+    let mut backoff = "fixed".to_string();
+    let mut delay = 100u64;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("times") {
+            times = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+        } else if meta.path.is_ident("backoff") {
+            let lit = meta.value()?.parse::<LitStr>()?;
+            backoff = lit.value();
+            if !matches!(backoff.as_str(), "fixed" | "exp" | "jitter") {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "backoff must be \"fixed\", \"exp\" or \"jitter\"",
+                ));
+            }
+        } else if meta.path.is_ident("delay") {
+            delay = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+        } else {
+            return Err(meta.error("unsupported retry argument"));
+        }
+        Ok(())
+    });
+    syn::parse_macro_input!(attr with parser);
+
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+    let fn_name = &input.sig.ident;
+    let fn_block = &input.block;
+    let fn_vis = &input.vis;
+    let fn_sig = &input.sig;
+    let fn_attrs: Vec<_> = input
+        .attrs
+        .iter()
+        .filter(|a| !a.path().is_ident("retry"))
+        .collect();
+    let policy_fn = match backoff.as_str() {
+        "exp" => format_ident!("exponential"),
+        other => format_ident!("{}", other),
+    };
+
+    let policy = quote! {
+        let policy = libcommon::retry::RetryPolicy::#policy_fn(std::time::Duration::from_millis(#delay))
+            .with_times(#times)
+            .with_name(format!("retry fn({})", stringify!(#fn_name)));
+    };
+    let retry_code = if input.sig.asyncness.is_some() {
+        quote! {
+            libcommon::retry::retry_async(&policy, || async move #fn_block).await
+        }
+    } else {
+        quote! {
+            libcommon::retry::retry(&policy, || #fn_block)
+        }
+    };
+
+    let output = quote! {
+        #(#fn_attrs)*
+        #fn_vis #fn_sig {
+            #policy
+            #retry_code
+        }
+    };
+    output.into()
+}
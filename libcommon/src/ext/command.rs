@@ -1,5 +1,7 @@
 use std::ffi::OsStr;
 
+use crate::{ext::which, newerr, prelude::Result};
+
 pub struct Command;
 
 pub trait CommandInExt {
@@ -32,43 +34,306 @@ impl Command {
     ///
     /// Create a command from a program and a string of arguments.
     ///
-    /// The arguments are split by whitespace.
-    pub fn with_str(program: impl AsRef<OsStr>, args: impl AsRef<str>) -> std::process::Command {
-        let args = args.as_ref().split_whitespace();
-        Self::with_args(program, args)
+    /// The arguments are split like a POSIX shell, see [shell_split].
+    pub fn with_str(
+        program: impl AsRef<OsStr>,
+        args: impl AsRef<str>,
+    ) -> Result<std::process::Command> {
+        let args = shell_split(args.as_ref())?;
+        Ok(Self::with_args(program, args))
     }
 
     ///
     /// Create a command from a string.
     ///
+    /// The string is split like a POSIX shell, see [shell_split].
     /// The first word is the program, the rest are arguments.
-    /// panics if no arguments are provided.
-    pub fn from_str(command: impl AsRef<str>) -> std::process::Command {
-        let mut parts = command.as_ref().split_whitespace();
-        let program = parts.next().expect("No command provided");
-        Self::with_args(program, parts)
+    /// Returns an error if the string is malformed or contains no words.
+    ///
+    /// The program is looked up when the command runs, see [Command::from_str_resolved] to check it first.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(command: impl AsRef<str>) -> Result<std::process::Command> {
+        Self::from_args(&shell_split(command.as_ref())?)
+    }
+
+    ///
+    /// Same as [Command::from_str], but also expands environment variables, see [shell_split_env].
+    pub fn from_str_env(command: impl AsRef<str>) -> Result<std::process::Command> {
+        Self::from_args(&shell_split_env(command.as_ref())?)
+    }
+
+    ///
+    /// Same as [Command::from_str], but the program is resolved with [which] first,
+    /// and the command runs the resolved path, e.g. `npm.cmd` on Windows.
+    ///
+    /// Returns an error listing the searched directories if the program is not found.
+    pub fn from_str_resolved(command: impl AsRef<str>) -> Result<std::process::Command> {
+        Self::from_args_resolved(&shell_split(command.as_ref())?)
     }
 
     ///
     /// Create a command from a list of arguments.
     ///
     /// The first argument is the program, the rest are arguments.
-    /// panics if no arguments are provided.
-    pub fn from_args(args: &[impl AsRef<OsStr>]) -> std::process::Command {
-        if args.is_empty() {
-            panic!("No command provided")
+    /// Returns an error if no arguments are provided.
+    pub fn from_args(args: &[impl AsRef<OsStr>]) -> Result<std::process::Command> {
+        let (program, args) = split_program(args)?;
+        Ok(Self::with_args(program, args))
+    }
+
+    ///
+    /// Same as [Command::from_args], but the program is resolved with [which] first, see [Command::from_str_resolved].
+    pub fn from_args_resolved(args: &[impl AsRef<OsStr>]) -> Result<std::process::Command> {
+        let (program, args) = split_program(args)?;
+        Ok(Self::with_args(which(program)?, args))
+    }
+
+    ///
+    /// Whether the program is found in `PATH` and executable, see [which].
+    pub fn exists(program: impl AsRef<OsStr>) -> bool {
+        which(program).is_ok()
+    }
+}
+
+fn split_program<S: AsRef<OsStr>>(args: &[S]) -> Result<(&S, &[S])> {
+    args.split_first()
+        .ok_or_else(|| newerr!(kind = InvalidInput, "No command provided"))
+}
+
+///
+/// Split a string into words like a POSIX shell.
+///
+/// - Whitespace separates words, unless quoted or escaped.
+/// - Single quotes keep everything literally until the next single quote.
+/// - Double quotes keep everything literally, except that a backslash escapes `$`, `` ` ``, `"`, `\` and newline.
+/// - Outside of quotes a backslash escapes any character; a backslash before a newline joins the lines.
+///
+/// Returns an error on unterminated quotes or a trailing backslash.
+/// Pipes, redirections and other operators are not interpreted.
+///
+/// # example
+/// ```
+/// use libcommon::ext::shell_split;
+///
+/// let words = shell_split(r#"git commit -m "two words" 'a b'\ c"#).unwrap();
+/// assert_eq!(words, ["git", "commit", "-m", "two words", "a b c"]);
+/// ```
+pub fn shell_split(s: &str) -> Result<Vec<String>> {
+    ShellSplitter::new(s, false, false).split_words()
+}
+
+///
+/// Same as [shell_split], but also expands `$NAME` and `${NAME}` outside of single quotes.
+///
+/// Unset variables expand to an empty string. Expanded values are never split into several words.
+pub fn shell_split_env(s: &str) -> Result<Vec<String>> {
+    ShellSplitter::new(s, true, false).split_words()
+}
+
+///
+/// Same as [shell_split], but also splits the words into commands at unquoted `|`.
+///
+/// Returns an error if any command is empty.
+pub(crate) fn shell_split_pipeline(s: &str) -> Result<Vec<Vec<String>>> {
+    ShellSplitter::new(s, false, true).split()
+}
+
+///
+/// Quote a word so that [shell_split] returns it unchanged.
+///
+/// Words made of safe characters only are returned as is.
+pub fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+struct ShellSplitter<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    env: bool,
+    /// Whether an unquoted `|` separates commands.
+    pipe: bool,
+    stages: Vec<Vec<String>>,
+    words: Vec<String>,
+    word: String,
+    /// Whether a word has started, so that `""` produces an empty word.
+    in_word: bool,
+}
+
+impl<'a> ShellSplitter<'a> {
+    fn new(s: &'a str, env: bool, pipe: bool) -> Self {
+        Self {
+            chars: s.chars().peekable(),
+            env,
+            pipe,
+            stages: vec![],
+            words: vec![],
+            word: String::new(),
+            in_word: false,
+        }
+    }
+
+    fn split_words(self) -> Result<Vec<String>> {
+        Ok(self.split()?.pop().unwrap_or_default())
+    }
+
+    fn split(mut self) -> Result<Vec<Vec<String>>> {
+        while let Some(c) = self.chars.next() {
+            match c {
+                c if c.is_whitespace() => self.end_word(),
+                '|' if self.pipe => {
+                    self.end_word();
+                    self.end_stage()?;
+                }
+                '\'' => {
+                    self.in_word = true;
+                    self.single_quoted()?;
+                }
+                '"' => {
+                    self.in_word = true;
+                    self.double_quoted()?;
+                }
+                '\\' => match self.chars.next() {
+                    Some('\n') => {}
+                    Some(c) => {
+                        self.in_word = true;
+                        self.word.push(c);
+                    }
+                    None => return Err(newerr!(kind = InvalidInput, "trailing backslash")),
+                },
+                '$' if self.env => {
+                    self.in_word = true;
+                    self.expand()?;
+                }
+                c => {
+                    self.in_word = true;
+                    self.word.push(c);
+                }
+            }
+        }
+        self.end_word();
+        if self.pipe {
+            self.end_stage()?;
+        } else {
+            self.stages.push(self.words);
         }
-        Self::with_args(args[0].as_ref(), &args[1..])
+        Ok(self.stages)
+    }
+
+    fn end_word(&mut self) {
+        if self.in_word {
+            self.words.push(std::mem::take(&mut self.word));
+            self.in_word = false;
+        }
+    }
+
+    fn end_stage(&mut self) -> Result<()> {
+        if self.words.is_empty() {
+            return Err(newerr!(kind = InvalidInput, "empty command in pipeline"));
+        }
+        self.stages.push(std::mem::take(&mut self.words));
+        Ok(())
+    }
+
+    fn single_quoted(&mut self) -> Result<()> {
+        for c in self.chars.by_ref() {
+            if c == '\'' {
+                return Ok(());
+            }
+            self.word.push(c);
+        }
+        Err(newerr!(kind = InvalidInput, "unterminated single quote"))
+    }
+
+    fn double_quoted(&mut self) -> Result<()> {
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' => return Ok(()),
+                '\\' => match self.chars.next() {
+                    Some('\n') => {}
+                    Some(c @ ('$' | '`' | '"' | '\\')) => self.word.push(c),
+                    Some(c) => {
+                        self.word.push('\\');
+                        self.word.push(c);
+                    }
+                    None => break,
+                },
+                '$' if self.env => self.expand()?,
+                c => self.word.push(c),
+            }
+        }
+        Err(newerr!(kind = InvalidInput, "unterminated double quote"))
+    }
+
+    /// Expand the variable after `$`, a lone `$` is kept.
+    fn expand(&mut self) -> Result<()> {
+        let is_name = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+        let mut name = String::new();
+        if self.chars.next_if_eq(&'{').is_some() {
+            loop {
+                match self.chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(newerr!(kind = InvalidInput, "unterminated ${{")),
+                }
+            }
+        } else {
+            while let Some(c) = self.chars.next_if(is_name) {
+                name.push(c);
+            }
+            if name.is_empty() {
+                self.word.push('$');
+                return Ok(());
+            }
+        }
+        self.word
+            .push_str(&std::env::var(&name).unwrap_or_default());
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_split() -> Result<()> {
+        assert_eq!(
+            shell_split(r#"git commit -m "two words" --author='A B <a@b.c>'"#)?,
+            ["git", "commit", "-m", "two words", "--author=A B <a@b.c>"]
+        );
+        assert_eq!(
+            shell_split(
+                r#"a\ b "c\"d\e" '' "" x\
+y"#
+            )?,
+            ["a b", "c\"d\\e", "", "", "xy"]
+        );
+        assert!(shell_split("  ")?.is_empty());
+        assert!(shell_split("'a").is_err());
+        assert!(shell_split("\"a").is_err());
+        assert!(shell_split("a\\").is_err());
+
+        unsafe { std::env::set_var("SHELL_SPLIT_TEST", "v 1") };
+        assert_eq!(
+            shell_split_env(
+                r#"$SHELL_SPLIT_TEST "${SHELL_SPLIT_TEST}x" '$SHELL_SPLIT_TEST' $ $1"#
+            )?,
+            ["v 1", "v 1x", "$SHELL_SPLIT_TEST", "$", ""]
+        );
+        assert_eq!(shell_split("$SHELL_SPLIT_TEST")?, ["$SHELL_SPLIT_TEST"]);
+
+        assert!(Command::from_str("").is_err());
+        let cmd = Command::from_str("ls 'my dir'")?;
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["my dir"]);
+        Ok(())
+    }
 
     #[test]
     #[cfg(target_os = "windows")]
-    fn test_command() {
-        use super::*;
+    fn test_command() -> Result<()> {
         use crate::ext::PrettyStringExt;
 
         fn test(mut cmd: std::process::Command) {
@@ -78,13 +343,14 @@ mod tests {
             println!("{} => {result:?}", cmd.to_string_pretty())
         }
 
-        test(Command::from_str("cmd /C echo 12345"));
-        test(Command::from_args(&["cmd", "/C", "echo", "12345"]));
-        test(Command::with_str("cmd", "/C echo 12345"));
+        test(Command::from_str("cmd /C echo 12345")?);
+        test(Command::from_args(&["cmd", "/C", "echo", "12345"])?);
+        test(Command::with_str("cmd", "/C echo 12345")?);
         test(Command::with_args("cmd", &["/C", "echo", "12345"]));
 
-        test(Command::from_str("cmd /C netstat -ano | findstr 8080"));
+        test(Command::from_str("cmd /C netstat -ano | findstr 8080")?);
 
-        test(Command::with_str("cmd", "/C netstat -ano | findstr 8080")); // 管道命令需要指定shell，如以cmd为程序
+        test(Command::with_str("cmd", "/C netstat -ano | findstr 8080")?); // 管道命令需要指定shell，如以cmd为程序，或者使用Pipeline
+        Ok(())
     }
 }
@@ -0,0 +1,300 @@
+use crate::if_feature;
+
+if_feature!("tokio" =>
+    use std::{ffi::OsStr, panic::Location, process::Stdio, time::{Duration, Instant}};
+
+    use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+    use crate::{
+        ext::{
+            Command, CommandLog, CommandOutput, PrettyStringExt, command_run::timeout_error,
+            log_line, spawn_error,
+        },
+        prelude::{ErrContextExt, Result},
+    };
+
+    ///
+    /// Create [tokio::process::Command]s, the same as [Command].
+    pub struct AsyncCommand;
+
+    impl AsyncCommand {
+        pub fn with_args<I, S>(program: impl AsRef<OsStr>, args: I) -> tokio::process::Command
+        where
+            I: IntoIterator<Item = S>,
+            S: AsRef<OsStr>,
+        {
+            Command::with_args(program, args).into()
+        }
+
+        ///
+        /// See [Command::with_str].
+        pub fn with_str(
+            program: impl AsRef<OsStr>,
+            args: impl AsRef<str>,
+        ) -> Result<tokio::process::Command> {
+            Ok(Command::with_str(program, args)?.into())
+        }
+
+        ///
+        /// See [Command::from_str].
+        #[allow(clippy::should_implement_trait)]
+        pub fn from_str(command: impl AsRef<str>) -> Result<tokio::process::Command> {
+            Ok(Command::from_str(command)?.into())
+        }
+
+        ///
+        /// See [Command::from_str_env].
+        pub fn from_str_env(command: impl AsRef<str>) -> Result<tokio::process::Command> {
+            Ok(Command::from_str_env(command)?.into())
+        }
+
+        ///
+        /// See [Command::from_str_resolved].
+        pub fn from_str_resolved(command: impl AsRef<str>) -> Result<tokio::process::Command> {
+            Ok(Command::from_str_resolved(command)?.into())
+        }
+
+        ///
+        /// See [Command::from_args].
+        pub fn from_args(args: &[impl AsRef<OsStr>]) -> Result<tokio::process::Command> {
+            Ok(Command::from_args(args)?.into())
+        }
+
+        ///
+        /// See [Command::from_args_resolved].
+        pub fn from_args_resolved(args: &[impl AsRef<OsStr>]) -> Result<tokio::process::Command> {
+            Ok(Command::from_args_resolved(args)?.into())
+        }
+    }
+
+    ///
+    /// Run a [tokio::process::Command] and capture its output,
+    /// the async version of [crate::ext::CommandRunExt] and [crate::ext::CommandLogExt].
+    ///
+    /// The child is killed if the returned future is dropped before the child exits,
+    /// so a command can be cancelled by dropping the future, e.g. with [tokio::select].
+    ///
+    /// # example
+    ///
+    /// ```ignore
+    /// let output = AsyncCommand::from_str("cargo build")?
+    ///     .run_timeout(Duration::from_secs(600))
+    ///     .await?
+    ///     .check()?;
+    /// ```
+    pub trait AsyncCommandRunExt {
+        ///
+        /// Run the command and wait for it to exit, a non-zero exit is not an error.
+        fn run(&mut self) -> impl Future<Output = Result<CommandOutput>> + Send;
+
+        ///
+        /// Run the command, a non-zero exit is returned as an error, see [CommandOutput::check].
+        ///
+        /// The error records the location of this call, not of the `.await`.
+        #[track_caller]
+        fn run_checked(&mut self) -> impl Future<Output = Result<CommandOutput>> + Send;
+
+        ///
+        /// Run the command, kill it if it does not exit within `timeout`.
+        ///
+        /// Returns an error of [crate::prelude::ErrorKind::Timeout] after killing the command,
+        /// with the tail of the stderr read before, the same as [crate::ext::CommandRunExt::run_timeout].
+        /// The command is not done until its stdout and stderr are closed.
+        fn run_timeout(
+            &mut self,
+            timeout: Duration,
+        ) -> impl Future<Output = Result<CommandOutput>> + Send;
+
+        ///
+        /// Run the command and forward each line of its stdout and stderr to the logger, see [CommandLog].
+        fn run_logged_with(
+            &mut self,
+            config: CommandLog,
+        ) -> impl Future<Output = Result<CommandOutput>> + Send;
+
+        fn run_logged(&mut self) -> impl Future<Output = Result<CommandOutput>> + Send {
+            self.run_logged_with(CommandLog::default())
+        }
+    }
+
+    impl AsyncCommandRunExt for tokio::process::Command {
+        async fn run(&mut self) -> Result<CommandOutput> {
+            let command = self.as_std().to_string_pretty();
+            let start = Instant::now();
+            let output = self
+                .kill_on_drop(true)
+                .output()
+                .await
+                .map_err(|e| spawn_error(self.as_std().get_program(), e))
+                .newerr_ctx(|| format!("failed to run `{command}`"))?;
+            Ok(CommandOutput {
+                command,
+                status: output.status,
+                stdout: output.stdout,
+                stderr: output.stderr,
+                elapsed: start.elapsed(),
+            })
+        }
+
+        #[track_caller]
+        fn run_checked(&mut self) -> impl Future<Output = Result<CommandOutput>> + Send {
+            // Captured before the future is polled, the location is lost across `.await`
+            let location = Location::caller();
+            async move { self.run().await?.check_at(location) }
+        }
+
+        async fn run_timeout(&mut self, timeout: Duration) -> Result<CommandOutput> {
+            let command = self.as_std().to_string_pretty();
+            let start = Instant::now();
+            let mut child = self
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| spawn_error(self.as_std().get_program(), e))
+                .newerr_ctx(|| format!("failed to run `{command}`"))?;
+            let (mut stdout_pipe, mut stderr_pipe) = (child.stdout.take(), child.stderr.take());
+            let (mut stdout, mut stderr) = (vec![], vec![]);
+            // Read in the same future, the bytes read stay in the buffers when it times out
+            let exited = tokio::time::timeout(timeout, async {
+                let (status, _, _) = tokio::join!(
+                    child.wait(),
+                    read_into(stdout_pipe.as_mut(), &mut stdout),
+                    read_into(stderr_pipe.as_mut(), &mut stderr),
+                );
+                status
+            })
+            .await;
+            if let Ok(status) = exited {
+                let status = status.newerr_ctx(|| format!("failed to wait for `{command}`"))?;
+                return Ok(CommandOutput {
+                    command,
+                    status,
+                    stdout,
+                    stderr,
+                    elapsed: start.elapsed(),
+                });
+            }
+            let _ = child.kill().await;
+            // Processes started by the command may still hold the pipes open, so do not wait for them long
+            let rest = read_into(stderr_pipe.as_mut(), &mut stderr);
+            let _ = tokio::time::timeout(Duration::from_millis(100), rest).await;
+            Err(timeout_error(&command, timeout, &stderr))
+        }
+
+        async fn run_logged_with(&mut self, config: CommandLog) -> Result<CommandOutput> {
+            let command = self.as_std().to_string_pretty();
+            let target = config.target_for(self.as_std().get_program());
+            let start = Instant::now();
+            let mut child = self
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| spawn_error(self.as_std().get_program(), e))
+                .newerr_ctx(|| format!("failed to run `{command}`"))?;
+            let (stdout, stderr) = tokio::join!(
+                log_lines(child.stdout.take(), config.stdout_level(), &target),
+                log_lines(child.stderr.take(), config.stderr_level(), &target),
+            );
+            let status = child.wait().await?;
+            Ok(CommandOutput {
+                command,
+                status,
+                stdout,
+                stderr,
+                elapsed: start.elapsed(),
+            })
+        }
+    }
+
+    /// Append the bytes of the pipe to `buf` until it is closed.
+    ///
+    /// Each chunk is appended as soon as it is read, so `buf` keeps the bytes read when the future is dropped.
+    async fn read_into(pipe: Option<&mut (impl AsyncRead + Unpin)>, buf: &mut Vec<u8>) {
+        let Some(pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0u8; 8192];
+        while let Ok(n) = pipe.read(&mut chunk).await {
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Log each line of the pipe, return all bytes read.
+    async fn log_lines(
+        pipe: Option<impl AsyncRead + Unpin>,
+        level: log::Level,
+        target: &str,
+    ) -> Vec<u8> {
+        let mut output = vec![];
+        let Some(pipe) = pipe else {
+            return output;
+        };
+        let mut reader = BufReader::new(pipe);
+        let mut line = vec![];
+        while let Ok(n) = reader.read_until(b'\n', &mut line).await {
+            if n == 0 {
+                break;
+            }
+            log_line(level, target, &line);
+            output.append(&mut line);
+        }
+        output
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::if_feature;
+
+    if_feature!("tokio" =>
+        use std::time::{Duration, Instant};
+
+        use super::*;
+        use crate::{log::log_setup, prelude::{ErrInfoExt, ErrorKind}};
+
+        #[test]
+        #[cfg(unix)]
+        fn test_async_command() -> Result<()> {
+            log_setup();
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(async {
+                let output = AsyncCommand::from_str("echo 'a b'")?.run_checked().await?;
+                assert_eq!(output.stdout_lossy(), "a b\n");
+
+                let mut command = AsyncCommand::with_str("sh", "-c 'echo oops >&2; exit 2'")?;
+                let line = line!() + 1;
+                let err = command.run_checked().await.unwrap_err();
+                assert!(err.to_string().contains("exit code 2:\noops"));
+                assert_eq!(err.location().map(|l| (l.file(), l.line())), Some((file!(), line)));
+
+                let start = Instant::now();
+                let err = AsyncCommand::from_str("sh -c 'echo started >&2; sleep 5'")?
+                    .run_timeout(Duration::from_millis(300))
+                    .await
+                    .unwrap_err();
+                assert!(err.is_kind(ErrorKind::Timeout));
+                assert!(err.to_string().ends_with("timed out after 300ms:\nstarted"));
+                assert!(start.elapsed() < Duration::from_secs(2));
+
+                let output = AsyncCommand::from_str("sh -c 'echo out; exit 3'")?
+                    .run_timeout(Duration::from_secs(5))
+                    .await?;
+                assert_eq!((output.code(), output.stdout_lossy().as_str()), (Some(3), "out\n"));
+
+                let output = AsyncCommand::from_str("sh -c 'echo out; echo err >&2'")?
+                    .run_logged()
+                    .await?;
+                assert_eq!(output.stdout_lossy(), "out\n");
+                assert_eq!(output.stderr_lossy(), "err\n");
+                Ok(())
+            })
+        }
+    );
+}
@@ -0,0 +1,317 @@
+use std::{
+    io,
+    os::unix::process::CommandExt,
+    process::{Child, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+use macro_builder::With;
+
+use crate::{
+    ext::{CommandOutput, PrettyStringExt, read_in_thread, spawn_error},
+    newerr,
+    prelude::{ErrContextExt, Result},
+};
+
+///
+/// How [CommandGroupExt] spawns a child.
+///
+/// # example
+///
+/// ```ignore
+/// let options = GroupOptions::default()
+///     .with_session(true)
+///     .with_parent_death(true)
+///     .with_grace(Duration::from_secs(5));
+/// let child = Command::from_str("sh -c 'server & worker'")?.spawn_group_with(options)?;
+/// ```
+#[derive(With, Debug, Clone)]
+pub struct GroupOptions {
+    /**
+     * Start a new session with `setsid` instead of only a new process group,
+     * which also detaches the child from the controlling terminal.
+     */
+    session: bool,
+    /**
+     * Kill the child with `SIGKILL` when the thread that spawned it exits, with `PR_SET_PDEATHSIG`.
+     *
+     * This only applies to the child itself, and fires when the spawning thread exits,
+     * so only enable it when spawning from a long-lived thread.
+     */
+    parent_death: bool,
+    /**
+     * The time between `SIGTERM` and `SIGKILL` when terminating the group.
+     *
+     * Dropping a [GroupChild] blocks for up to this long.
+     */
+    grace: Duration,
+}
+
+impl Default for GroupOptions {
+    fn default() -> Self {
+        Self {
+            session: false,
+            parent_death: false,
+            grace: Duration::from_secs(2),
+        }
+    }
+}
+
+///
+/// A child running in its own process group.
+///
+/// Dropping it terminates the whole group, see [GroupChild::terminate],
+/// so processes started by the child do not outlive it.
+/// Dropping blocks for up to the grace time if processes ignore `SIGTERM`, see [GroupOptions].
+pub struct GroupChild {
+    child: Child,
+    pgid: libc::pid_t,
+    grace: Duration,
+    terminated: bool,
+}
+
+impl GroupChild {
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    ///
+    /// The child, e.g. to take its stdout. Do not wait for it directly, use [GroupChild::wait] instead.
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    ///
+    /// Wait for the child to exit, the rest of the group is not waited for.
+    pub fn wait(&mut self) -> Result<ExitStatus> {
+        Ok(self.child.wait()?)
+    }
+
+    ///
+    /// Wait for the child to exit, returns `None` if it is still running after `timeout`.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Option<ExitStatus>> {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if start.elapsed() >= timeout {
+                return Ok(None);
+            }
+            std::thread::sleep(
+                Duration::from_millis(10).min(timeout.saturating_sub(start.elapsed())),
+            );
+        }
+    }
+
+    ///
+    /// Send `SIGTERM` to the whole group, then `SIGKILL` if any process is still alive after the grace time.
+    ///
+    /// No signal is sent once the group is empty, since its id may be reused by another group.
+    /// It does nothing if called again.
+    pub fn terminate(&mut self) {
+        if self.terminated {
+            return;
+        }
+        self.terminated = true;
+        // Reap the child, otherwise it is still counted in the group as a zombie
+        let _ = self.child.try_wait();
+        if !self.alive() {
+            return;
+        }
+        self.signal(libc::SIGTERM);
+        let start = Instant::now();
+        loop {
+            let _ = self.child.try_wait();
+            if !self.alive() {
+                return;
+            }
+            if start.elapsed() >= self.grace {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        self.signal(libc::SIGKILL);
+        let _ = self.child.wait();
+    }
+
+    fn signal(&self, signal: libc::c_int) {
+        // SAFETY: killpg has no memory safety requirements, a missing group only returns ESRCH
+        unsafe {
+            libc::killpg(self.pgid, signal);
+        }
+    }
+
+    /// Whether any process of the group exists.
+    fn alive(&self) -> bool {
+        // SAFETY: signal 0 only checks the existence of the group
+        unsafe { libc::killpg(self.pgid, 0) == 0 }
+    }
+}
+
+impl Drop for GroupChild {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+///
+/// Spawn a [std::process::Command] in its own process group, Linux only.
+///
+/// # example
+/// ```
+/// use std::time::Duration;
+/// use libcommon::ext::{Command, CommandGroupExt};
+///
+/// let err = Command::from_str("sh -c 'sleep 30 & sleep 30'")
+///     .unwrap()
+///     .run_group_timeout(Duration::from_millis(100))
+///     .unwrap_err();
+/// assert!(err.to_string().contains("timed out"));
+/// ```
+pub trait CommandGroupExt {
+    fn spawn_group(&mut self) -> Result<GroupChild> {
+        self.spawn_group_with(GroupOptions::default())
+    }
+
+    fn spawn_group_with(&mut self, options: GroupOptions) -> Result<GroupChild>;
+
+    ///
+    /// Run the command in its own process group and capture its output,
+    /// terminate the whole group if the command does not exit within `timeout`.
+    ///
+    /// Processes left in the group after the command exits are terminated too.
+    /// Returns an error of [crate::prelude::ErrorKind::Timeout] after terminating the group.
+    fn run_group_timeout(&mut self, timeout: Duration) -> Result<CommandOutput>;
+}
+
+impl CommandGroupExt for std::process::Command {
+    fn spawn_group_with(&mut self, options: GroupOptions) -> Result<GroupChild> {
+        let GroupOptions {
+            session,
+            parent_death,
+            grace,
+        } = options;
+        if !session {
+            self.process_group(0);
+        }
+        // SAFETY: only async-signal-safe functions are called between fork and exec
+        unsafe {
+            self.pre_exec(move || {
+                if session && libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if parent_death && libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let command = self.to_string_pretty();
+        let child = self
+            .spawn()
+            .map_err(|e| spawn_error(self.get_program(), e))
+            .newerr_ctx(|| format!("failed to run `{command}`"))?;
+        Ok(GroupChild {
+            pgid: child.id() as libc::pid_t,
+            child,
+            grace,
+            terminated: false,
+        })
+    }
+
+    fn run_group_timeout(&mut self, timeout: Duration) -> Result<CommandOutput> {
+        let command = self.to_string_pretty();
+        let start = Instant::now();
+        let mut group = self
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn_group()?;
+        let stdout = read_in_thread(group.child().stdout.take());
+        let stderr = read_in_thread(group.child().stderr.take());
+        let status = group.wait_timeout(timeout)?;
+        // Processes left in the group may hold the pipes open
+        group.terminate();
+        let Some(status) = status else {
+            return Err(newerr!(
+                kind = Timeout,
+                "command `{command}` timed out after {timeout:?}"
+            ));
+        };
+        Ok(CommandOutput {
+            command,
+            status,
+            stdout: stdout.read_all(),
+            stderr: stderr.read_all(),
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ext::Command, prelude::ErrInfoExt};
+
+    /// Whether the process exists and is not a zombie.
+    fn running(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+            stat.rsplit(')')
+                .next()
+                .is_some_and(|s| !s.trim_start().starts_with('Z'))
+        })
+    }
+
+    #[test]
+    fn test_group() -> Result<()> {
+        let options = GroupOptions::default().with_grace(Duration::from_millis(200));
+        let mut group = Command::from_str("sh -c 'sleep 30 & echo $!; wait'")?
+            .stdout(Stdio::piped())
+            .spawn_group_with(options)?;
+        let mut stdout = group.child().stdout.take().unwrap();
+        let mut line = String::new();
+        while !line.ends_with('\n') {
+            let mut buf = [0u8; 1];
+            if io::Read::read(&mut stdout, &mut buf)? == 0 {
+                break;
+            }
+            line.push(buf[0] as char);
+        }
+        let grandchild = line.trim().to_string();
+        assert!(running(&grandchild));
+        assert!(group.wait_timeout(Duration::from_millis(50))?.is_none());
+        drop(group);
+        assert!(!running(&grandchild));
+
+        let output = Command::from_str("sh -c 'sleep 30 & echo done'")?
+            .run_group_timeout(Duration::from_secs(5))?;
+        assert_eq!(output.stdout_lossy(), "done\n");
+        assert!(output.elapsed < Duration::from_secs(5));
+
+        let err = Command::from_str("sh -c 'sleep 30'")?
+            .run_group_timeout(Duration::from_millis(100))
+            .unwrap_err();
+        assert!(err.is_kind(crate::prelude::ErrorKind::Timeout));
+
+        // The group is already empty, no signal is sent and the grace time is not waited
+        let options = GroupOptions::default().with_grace(Duration::from_secs(5));
+        let mut group = Command::from_str("true")?.spawn_group_with(options)?;
+        assert!(group.wait()?.success());
+        let start = Instant::now();
+        drop(group);
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // SIGTERM is ignored, SIGKILL is sent after the grace time
+        let options = GroupOptions::default().with_grace(Duration::from_millis(200));
+        let mut group =
+            Command::from_str("sh -c 'trap \"\" TERM; sleep 30'")?.spawn_group_with(options)?;
+        let pid = group.id().to_string();
+        std::thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        group.terminate();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(!running(&pid));
+        Ok(())
+    }
+}
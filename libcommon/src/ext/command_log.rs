@@ -0,0 +1,173 @@
+use std::{
+    ffi::OsStr,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::Stdio,
+    thread::JoinHandle,
+    time::Instant,
+};
+
+use macro_builder::With;
+
+use crate::{
+    ext::{CommandOutput, PrettyStringExt, spawn_error},
+    prelude::{ErrContextExt, ErrMapperExt, Result},
+};
+
+///
+/// How [CommandLogExt] forwards the output of a command to the logger.
+///
+/// By default stdout lines are logged at `info` and stderr lines at `warn`,
+/// under the target `cmd:<program>`, e.g. `cmd:cargo`.
+///
+/// # example
+///
+/// ```ignore
+/// let config = CommandLog::default()
+///     .with_stdout(log::Level::Debug)
+///     .with_target("build".to_string());
+/// Command::from_str("cargo build")?.run_logged_with(config)?.check()?;
+/// ```
+#[derive(With, Clone)]
+pub struct CommandLog {
+    /**
+     * The level of stdout lines.
+     */
+    stdout: log::Level,
+    /**
+     * The level of stderr lines.
+     */
+    stderr: log::Level,
+    /**
+     * The log target, `cmd:<program>` if not set.
+     */
+    target: Option<String>,
+}
+
+impl Default for CommandLog {
+    fn default() -> Self {
+        Self {
+            stdout: log::Level::Info,
+            stderr: log::Level::Warn,
+            target: None,
+        }
+    }
+}
+
+impl CommandLog {
+    pub(crate) fn target_for(&self, program: &OsStr) -> String {
+        self.target.clone().unwrap_or_else(|| {
+            let program = Path::new(program);
+            let name = program.file_name().unwrap_or(program.as_os_str());
+            format!("cmd:{}", name.to_string_lossy())
+        })
+    }
+
+    pub(crate) fn stdout_level(&self) -> log::Level {
+        self.stdout
+    }
+
+    pub(crate) fn stderr_level(&self) -> log::Level {
+        self.stderr
+    }
+}
+
+/// Log a line of output, the line ending is removed.
+pub(crate) fn log_line(level: log::Level, target: &str, line: &[u8]) {
+    let text = String::from_utf8_lossy(line);
+    log::log!(target: target, level, "{}", text.trim_end_matches(['\r', '\n']));
+}
+
+///
+/// Run a [std::process::Command] and forward each line of its stdout and stderr
+/// to the logger as soon as it arrives, see [CommandLog].
+///
+/// The output is also collected and returned, stdin is closed.
+/// A non-zero exit is not an error, use [CommandOutput::check] if needed.
+pub trait CommandLogExt {
+    fn run_logged(&mut self) -> Result<CommandOutput> {
+        self.run_logged_with(CommandLog::default())
+    }
+
+    fn run_logged_with(&mut self, config: CommandLog) -> Result<CommandOutput>;
+}
+
+impl CommandLogExt for std::process::Command {
+    fn run_logged_with(&mut self, config: CommandLog) -> Result<CommandOutput> {
+        let command = self.to_string_pretty();
+        let target = config.target_for(self.get_program());
+        let start = Instant::now();
+        let mut child = self
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_error(self.get_program(), e))
+            .newerr_ctx(|| format!("failed to run `{command}`"))?;
+        let stdout = log_lines(child.stdout.take(), config.stdout_level(), target.clone());
+        let stderr = log_lines(child.stderr.take(), config.stderr_level(), target);
+        let status = child.wait()?;
+        Ok(CommandOutput {
+            command,
+            status,
+            stdout: stdout.join().newerr()?,
+            stderr: stderr.join().newerr()?,
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+/// Log each line of the pipe in a new thread, return all bytes read.
+fn log_lines(
+    pipe: Option<impl Read + Send + 'static>,
+    level: log::Level,
+    target: String,
+) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut output = vec![];
+        let Some(pipe) = pipe else {
+            return output;
+        };
+        let mut reader = BufReader::new(pipe);
+        let mut line = vec![];
+        while let Ok(n) = reader.read_until(b'\n', &mut line) {
+            if n == 0 {
+                break;
+            }
+            log_line(level, &target, &line);
+            output.append(&mut line);
+        }
+        output
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ext::Command,
+        log::{log_setup, log_stats},
+    };
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_logged() -> Result<()> {
+        log_setup();
+        let before = log_stats();
+        let output =
+            Command::from_str("sh -c 'echo out1; echo err >&2; printf out2'")?.run_logged()?;
+        assert!(output.success());
+        assert_eq!(output.stdout_lossy(), "out1\nout2");
+        assert_eq!(output.stderr_lossy(), "err\n");
+        let after = log_stats();
+        assert!(after.info >= before.info + 2);
+        assert!(after.warn > before.warn);
+
+        let config = CommandLog::default()
+            .with_stderr(log::Level::Error)
+            .with_target("cmd:test".to_string());
+        let output = Command::from_str("sh -c 'exit 1'")?.run_logged_with(config)?;
+        assert_eq!(output.code(), Some(1));
+        Ok(())
+    }
+}
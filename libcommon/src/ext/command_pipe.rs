@@ -0,0 +1,297 @@
+use std::{
+    process::{Child, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ext::{
+        Command, CommandOutput, PrettyStringExt, read_in_thread, shell_split_pipeline, spawn_error,
+    },
+    newerr,
+    prelude::{ErrContextExt, ErrInfo, Result},
+};
+
+///
+/// Where the stderr of each command in a [Pipeline] goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PipeStderr {
+    /// Inherit the stderr of the current process.
+    #[default]
+    Inherit,
+    /// Capture into [CommandOutput::stderr] of each stage.
+    Capture,
+    /// Discard, like `2>/dev/null`.
+    Null,
+    /// Send to the same pipe as stdout, like `2>&1`.
+    Stdout,
+}
+
+///
+/// Commands connected by pipes, like `a | b | c` in a shell, but without invoking a shell.
+///
+/// The stdout of each command is connected directly to the stdin of the next one.
+/// The stdin of the first command is closed and the stdout of the last command is captured.
+///
+/// # example
+/// ```
+/// use libcommon::ext::{Command, Pipeline};
+///
+/// # #[cfg(unix)]
+/// # {
+/// let output = Pipeline::from_str("printf 'a\nfoo\nb foo\n' | grep foo | wc -l").unwrap().run().unwrap();
+/// assert!(output.success());
+/// assert_eq!(output.stdout_lossy().trim(), "2");
+///
+/// let output = Pipeline::new()
+///     .pipe(Command::from_str("echo a").unwrap())
+///     .pipe(Command::from_str("grep b").unwrap())
+///     .run()
+///     .unwrap();
+/// assert!(!output.success());
+/// assert!(output.check().is_err());
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<(std::process::Command, PipeStderr)>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Create a pipeline from a string.
+    ///
+    /// The string is split like a POSIX shell, see [crate::ext::shell_split],
+    /// and an unquoted `|` separates the commands.
+    /// Other operators such as redirections are not interpreted, use [Pipeline::stderr] instead.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(pipeline: impl AsRef<str>) -> Result<Self> {
+        let mut this = Self::new();
+        for words in shell_split_pipeline(pipeline.as_ref())? {
+            this = this.pipe(Command::with_args(&words[0], &words[1..]));
+        }
+        Ok(this)
+    }
+
+    ///
+    /// Append a command, its stderr is inherited.
+    pub fn pipe(mut self, command: std::process::Command) -> Self {
+        self.stages.push((command, PipeStderr::Inherit));
+        self
+    }
+
+    ///
+    /// Set the stderr of all commands added so far.
+    pub fn stderr(mut self, stderr: PipeStderr) -> Self {
+        self.stages.iter_mut().for_each(|(_, s)| *s = stderr);
+        self
+    }
+
+    ///
+    /// Set the stderr of the last command added.
+    pub fn last_stderr(mut self, stderr: PipeStderr) -> Self {
+        if let Some((_, s)) = self.stages.last_mut() {
+            *s = stderr;
+        }
+        self
+    }
+
+    ///
+    /// Run all commands and wait for them to exit.
+    ///
+    /// A non-zero exit of any command is not an error, see [PipelineOutput::check].
+    pub fn run(self) -> Result<PipelineOutput> {
+        if self.stages.is_empty() {
+            return Err(newerr!(kind = InvalidInput, "empty pipeline"));
+        }
+        let commands: Vec<String> = self
+            .stages
+            .iter()
+            .map(|(c, _)| c.to_string_pretty())
+            .collect();
+        let command = commands.join(" | ");
+        let start = Instant::now();
+        let last = self.stages.len() - 1;
+        let mut children = vec![];
+        let mut stdin = Stdio::null();
+        let mut stdout = None;
+        for (i, (mut cmd, stderr)) in self.stages.into_iter().enumerate() {
+            cmd.stdin(stdin);
+            let merged = match stderr {
+                PipeStderr::Stdout => {
+                    let (reader, writer) = std::io::pipe()?;
+                    cmd.stdout(writer.try_clone()?).stderr(writer);
+                    Some(reader)
+                }
+                PipeStderr::Capture => {
+                    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+                    None
+                }
+                PipeStderr::Null => {
+                    cmd.stdout(Stdio::piped()).stderr(Stdio::null());
+                    None
+                }
+                PipeStderr::Inherit => {
+                    cmd.stdout(Stdio::piped()).stderr(Stdio::inherit());
+                    None
+                }
+            };
+            let spawned = cmd
+                .spawn()
+                .map_err(|e| spawn_error(cmd.get_program(), e))
+                .newerr_ctx(|| format!("failed to run `{}` in pipeline `{command}`", commands[i]));
+            // Drop the command so that the write end of the pipe it holds is closed
+            drop(cmd);
+            let mut child: Child = match spawned {
+                Ok(child) => child,
+                Err(e) => {
+                    for (child, _) in children.iter_mut() {
+                        kill(child);
+                    }
+                    return Err(e);
+                }
+            };
+            let stderr = read_in_thread(child.stderr.take());
+            stdin = match (merged, i == last) {
+                (Some(reader), false) => reader.into(),
+                (None, false) => child.stdout.take().map_or_else(Stdio::null, Stdio::from),
+                (Some(reader), true) => {
+                    stdout = Some(read_in_thread(Some(reader)));
+                    Stdio::null()
+                }
+                (None, true) => {
+                    stdout = Some(read_in_thread(child.stdout.take()));
+                    Stdio::null()
+                }
+            };
+            children.push((child, stderr));
+        }
+
+        let mut stages = vec![];
+        for ((mut child, stderr), command) in children.into_iter().zip(commands) {
+            let status = child.wait()?;
+            stages.push(CommandOutput {
+                command,
+                status,
+                stdout: vec![],
+                stderr: stderr.read_all(),
+                elapsed: start.elapsed(),
+            });
+        }
+        Ok(PipelineOutput {
+            command,
+            stages,
+            stdout: stdout.map(|r| r.read_all()).unwrap_or_default(),
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+///
+/// The output of a finished [Pipeline].
+#[derive(Debug)]
+pub struct PipelineOutput {
+    /// The pretty pipeline, commands are separated by ` | `.
+    pub command: String,
+    /// The output of each command, [CommandOutput::stdout] is always empty.
+    pub stages: Vec<CommandOutput>,
+    /// The stdout of the last command.
+    pub stdout: Vec<u8>,
+    pub elapsed: Duration,
+}
+
+impl PipelineOutput {
+    ///
+    /// Whether all commands succeeded, like `set -o pipefail`.
+    pub fn success(&self) -> bool {
+        self.stages.iter().all(|s| s.success())
+    }
+
+    ///
+    /// The last command that failed, its status is the status of the pipeline with `set -o pipefail`.
+    pub fn failed_stage(&self) -> Option<&CommandOutput> {
+        self.stages.iter().rev().find(|s| !s.success())
+    }
+
+    /// Stdout as UTF-8, invalid sequences are replaced.
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// Stdout as UTF-8, returns an error on invalid sequences.
+    pub fn stdout_str(&self) -> Result<&str> {
+        std::str::from_utf8(&self.stdout).newerr_msg("stdout is not valid UTF-8")
+    }
+
+    ///
+    /// Return the output if all commands succeeded,
+    /// otherwise an error caused by the last failed command, see [CommandOutput::check].
+    #[track_caller]
+    pub fn check(mut self) -> Result<Self> {
+        let Some(i) = self.stages.iter().rposition(|s| !s.success()) else {
+            return Ok(self);
+        };
+        let stage = self.stages.remove(i);
+        let err = stage
+            .check()
+            .err()
+            .unwrap_or_else(|| newerr!("stage {i} failed"));
+        let msg = format!("pipeline `{}` failed at stage {}", self.command, i + 1);
+        Err(ErrInfo::wrap(err, msg).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::ErrInfoExt;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pipeline() -> Result<()> {
+        let output = Pipeline::from_str("printf 'a b\nc\n' | tr ' ' '|' | grep '|'")?.run()?;
+        assert!(output.success());
+        assert_eq!(output.stdout_lossy(), "a|b\n");
+        assert_eq!(output.stages.len(), 3);
+
+        let output = Pipeline::from_str("sh -c 'echo err >&2; exit 3' | cat")?
+            .stderr(PipeStderr::Capture)
+            .run()?;
+        assert!(!output.success());
+        assert_eq!(output.failed_stage().unwrap().code(), Some(3));
+        assert_eq!(output.stages[0].stderr_lossy(), "err\n");
+        let err = output.check().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pipeline `sh -c 'echo err >&2; exit 3' | cat` failed at stage 1"
+        );
+        assert_eq!(err.location().unwrap().file(), file!());
+
+        let output = Pipeline::from_str("sh -c 'echo err >&2' | cat")?
+            .stderr(PipeStderr::Stdout)
+            .run()?;
+        assert_eq!(output.stdout_lossy(), "err\n");
+
+        let output = Pipeline::from_str("sh -c 'echo err >&2'")?
+            .last_stderr(PipeStderr::Null)
+            .run()?;
+        assert!(output.success() && output.stdout.is_empty());
+
+        assert!(Pipeline::from_str("echo a | | cat").is_err());
+        assert!(Pipeline::from_str("echo a |").is_err());
+        assert!(Pipeline::new().run().is_err());
+        let err = Pipeline::from_str("echo a | not-exists-command")?
+            .run()
+            .unwrap_err();
+        assert!(err.is_kind(crate::prelude::ErrorKind::NotFound));
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    io::Read,
+    panic::Location,
+    process::{ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ext::{PrettyStringExt, spawn_error},
+    newerr,
+    prelude::{Err, ErrContextExt, ErrInfo, ErrorKind, Result},
+};
+
+/// The number of stderr lines kept in the error of a failed command.
+const STDERR_TAIL_LINES: usize = 20;
+
+///
+/// The output of a finished command, see [CommandRunExt].
+#[derive(Debug)]
+pub struct CommandOutput {
+    /// The pretty command line, see [PrettyStringExt].
+    pub command: String,
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// The time from spawning the command to its exit.
+    pub elapsed: Duration,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    ///
+    /// The exit code, `None` if the command was terminated by a signal.
+    pub fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    ///
+    /// The signal that terminated the command, always `None` on non-unix platforms.
+    pub fn signal(&self) -> Option<i32> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            self.status.signal()
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    /// Stdout as UTF-8, invalid sequences are replaced.
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// Stderr as UTF-8, invalid sequences are replaced.
+    pub fn stderr_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+
+    /// Stdout as UTF-8, returns an error on invalid sequences.
+    pub fn stdout_str(&self) -> Result<&str> {
+        std::str::from_utf8(&self.stdout).newerr_msg("stdout is not valid UTF-8")
+    }
+
+    /// Stderr as UTF-8, returns an error on invalid sequences.
+    pub fn stderr_str(&self) -> Result<&str> {
+        std::str::from_utf8(&self.stderr).newerr_msg("stderr is not valid UTF-8")
+    }
+
+    ///
+    /// Return the output if the command succeeded, otherwise an error that contains
+    /// the command line, the exit status and the tail of stderr.
+    #[track_caller]
+    pub fn check(self) -> Result<Self> {
+        self.check_at(Location::caller())
+    }
+
+    /// Same as [CommandOutput::check], the error records `location` instead of the caller.
+    pub(crate) fn check_at(self, location: &'static Location<'static>) -> Result<Self> {
+        if self.success() {
+            return Ok(self);
+        }
+        let status = match (self.code(), self.signal()) {
+            (Some(code), _) => format!("exit code {code}"),
+            (None, Some(signal)) => format!("signal {signal}"),
+            (None, None) => self.status.to_string(),
+        };
+        let mut msg = format!("command `{}` failed with {status}", self.command);
+        let tail = stderr_tail(&self.stderr);
+        if !tail.is_empty() {
+            msg.push_str(":\n");
+            msg.push_str(&tail);
+        }
+        let info = ErrInfo::new(ErrorKind::Other, msg).at(location);
+        let info = match self.code() {
+            Some(code) => info.with_kv("code", code),
+            None => info,
+        };
+        Err(info.into())
+    }
+}
+
+///
+/// The error of a command killed after `timeout`, with the tail of the stderr read before.
+#[track_caller]
+pub(crate) fn timeout_error(command: &str, timeout: Duration, stderr: &[u8]) -> Err {
+    let mut msg = format!("command `{command}` timed out after {timeout:?}");
+    let tail = stderr_tail(stderr);
+    if !tail.is_empty() {
+        msg.push_str(":\n");
+        msg.push_str(&tail);
+    }
+    newerr!(kind = Timeout, "{msg}")
+}
+
+/// The last lines of stderr.
+fn stderr_tail(stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let lines: Vec<&str> = stderr.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+}
+
+///
+/// Run a [std::process::Command] and capture its output.
+///
+/// Stdout and stderr are captured and stdin is closed, like [std::process::Command::output].
+///
+/// # example
+/// ```
+/// use libcommon::ext::{Command, CommandRunExt};
+///
+/// # #[cfg(unix)]
+/// # {
+/// let output = Command::from_str("echo hello").unwrap().run_checked().unwrap();
+/// assert_eq!(output.stdout_lossy(), "hello\n");
+///
+/// let err = Command::from_str("sh -c 'echo oops >&2; exit 3'").unwrap().run_checked().unwrap_err();
+/// assert!(err.to_string().contains("exit code 3:\noops"));
+/// # }
+/// ```
+pub trait CommandRunExt {
+    ///
+    /// Run the command and wait for it to exit.
+    ///
+    /// A non-zero exit is not an error, see [CommandRunExt::run_checked].
+    fn run(&mut self) -> Result<CommandOutput>;
+
+    ///
+    /// Run the command, a non-zero exit is returned as an error, see [CommandOutput::check].
+    #[track_caller]
+    fn run_checked(&mut self) -> Result<CommandOutput> {
+        self.run()?.check()
+    }
+
+    ///
+    /// Run the command, kill it if it does not exit within `timeout`.
+    ///
+    /// Returns an error of [ErrorKind::Timeout] after killing the command.
+    /// A non-zero exit is not an error, use [CommandOutput::check] if needed.
+    ///
+    /// Processes started by the command are not killed. If they keep its stdout or stderr open
+    /// after it exits, the output read within `timeout` is returned without waiting for them.
+    fn run_timeout(&mut self, timeout: Duration) -> Result<CommandOutput>;
+}
+
+impl CommandRunExt for std::process::Command {
+    fn run(&mut self) -> Result<CommandOutput> {
+        let command = self.to_string_pretty();
+        let start = Instant::now();
+        let output = self
+            .output()
+            .map_err(|e| spawn_error(self.get_program(), e))
+            .newerr_ctx(|| format!("failed to run `{command}`"))?;
+        Ok(CommandOutput {
+            command,
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    fn run_timeout(&mut self, timeout: Duration) -> Result<CommandOutput> {
+        let command = self.to_string_pretty();
+        let start = Instant::now();
+        let mut child = self
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_error(self.get_program(), e))
+            .newerr_ctx(|| format!("failed to run `{command}`"))?;
+        let stdout = read_in_thread(child.stdout.take());
+        let stderr = read_in_thread(child.stderr.take());
+        loop {
+            let status = match child.try_wait() {
+                Ok(status) => status,
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(e).newerr_ctx(|| format!("failed to wait for `{command}`"));
+                }
+            };
+            if let Some(status) = status {
+                return Ok(CommandOutput {
+                    command,
+                    status,
+                    stdout: stdout.read_timeout(timeout.saturating_sub(start.elapsed())),
+                    stderr: stderr.read_timeout(timeout.saturating_sub(start.elapsed())),
+                    elapsed: start.elapsed(),
+                });
+            }
+            if start.elapsed() >= timeout {
+                break;
+            }
+            std::thread::sleep(
+                Duration::from_millis(10).min(timeout.saturating_sub(start.elapsed())),
+            );
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        // Processes started by the command may still hold the pipes open, so do not wait for them long
+        let stderr = stderr.read_timeout(Duration::from_millis(100));
+        Err(timeout_error(&command, timeout, &stderr))
+    }
+}
+
+/// A pipe read to the end in a new thread, see [read_in_thread].
+pub(crate) struct PipeReader {
+    buf: Arc<Mutex<Vec<u8>>>,
+    /// Disconnected when the pipe is read to the end.
+    done: crossbeam_channel::Receiver<()>,
+}
+
+impl PipeReader {
+    /// Wait for the pipe to be closed, then return all bytes read.
+    pub(crate) fn read_all(&self) -> Vec<u8> {
+        let _ = self.done.recv();
+        self.take()
+    }
+
+    /// Wait at most `timeout` for the pipe to be closed, then return the bytes read so far.
+    pub(crate) fn read_timeout(&self, timeout: Duration) -> Vec<u8> {
+        let _ = self.done.recv_timeout(timeout);
+        self.take()
+    }
+
+    fn take(&self) -> Vec<u8> {
+        self.buf
+            .lock()
+            .map(|mut buf| std::mem::take(&mut *buf))
+            .unwrap_or_default()
+    }
+}
+
+/// Read the pipe to the end in a new thread, so that a full pipe does not block the command.
+pub(crate) fn read_in_thread(pipe: Option<impl Read + Send + 'static>) -> PipeReader {
+    let buf = Arc::new(Mutex::new(vec![]));
+    let (tx, done) = crossbeam_channel::bounded::<()>(0);
+    let shared = buf.clone();
+    std::thread::spawn(move || {
+        // Dropped when the pipe is read to the end
+        let _tx = tx;
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut chunk = [0u8; 8192];
+        while let Ok(n) = pipe.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            match shared.lock() {
+                Ok(mut buf) => buf.extend_from_slice(&chunk[..n]),
+                Err(_) => break,
+            }
+        }
+    });
+    PipeReader { buf, done }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ext::Command, prelude::ErrInfoExt};
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run() -> Result<()> {
+        let output = Command::from_str("sh -c 'printf out; printf err >&2; exit 2'")?.run()?;
+        assert!(!output.success());
+        assert_eq!(output.code(), Some(2));
+        assert_eq!(output.stdout_str()?, "out");
+        assert_eq!(output.stderr_lossy(), "err");
+
+        let err = Command::from_str("sh -c 'printf err >&2; exit 2'")?
+            .run_checked()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "command `sh -c 'printf err >&2; exit 2'` failed with exit code 2:\nerr"
+        );
+        assert_eq!(err.fields(), [("code".to_string(), "2".to_string())]);
+        assert_eq!(err.location().unwrap().file(), file!());
+
+        let output = Command::from_str("sh -c 'kill -9 $$'")?.run()?;
+        assert_eq!(output.signal(), Some(9));
+
+        let err = Command::from_str("not-exists-command")?.run().unwrap_err();
+        assert!(err.is_kind(ErrorKind::NotFound));
+        assert!(format!("{err:#}").contains("program `not-exists-command` not found in PATH"));
+
+        let output = Command::from_str("echo ok")?.run_timeout(Duration::from_secs(5))?;
+        assert_eq!(output.stdout_lossy(), "ok\n");
+        let start = Instant::now();
+        let err = Command::from_str("sleep 5")?
+            .run_timeout(Duration::from_millis(100))
+            .unwrap_err();
+        assert!(err.is_kind(ErrorKind::Timeout));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // The background process keeps the pipes open after the command exits
+        let start = Instant::now();
+        let output = Command::with_args("sh", ["-c", "sleep 5 & echo hi"])
+            .run_timeout(Duration::from_millis(300))?;
+        assert!(output.success());
+        assert_eq!(output.stdout_lossy(), "hi\n");
+        assert!(start.elapsed() < Duration::from_secs(2));
+        Ok(())
+    }
+}
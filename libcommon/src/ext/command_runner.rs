@@ -0,0 +1,293 @@
+use std::{
+    process::ExitStatus,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    ext::{CommandOutput, CommandRunExt, PrettyStringExt, shell_split},
+    newerr,
+    prelude::{ErrMapperExt, Result},
+};
+
+///
+/// Runs commands, so that code running commands can be tested with [FakeRunner].
+///
+/// # example
+///
+/// ```
+/// use libcommon::ext::{Command, CommandRunner, FakeRunner};
+/// use libcommon::prelude::Result;
+///
+/// fn branch(runner: &dyn CommandRunner) -> Result<String> {
+///     let output = runner.run_checked(&mut Command::from_str("git branch --show-current")?)?;
+///     Ok(output.stdout_lossy().trim().to_string())
+/// }
+///
+/// let fake = FakeRunner::new().on("git branch --show-current").stdout("main\n");
+/// assert_eq!(branch(&fake).unwrap(), "main");
+/// assert_eq!(fake.calls(), ["git branch --show-current"]);
+/// ```
+pub trait CommandRunner: Send + Sync {
+    ///
+    /// Run the command, a non-zero exit is not an error, see [CommandRunExt::run].
+    fn run(&self, command: &mut std::process::Command) -> Result<CommandOutput>;
+
+    ///
+    /// Run the command, a non-zero exit is returned as an error, see [CommandOutput::check].
+    fn run_checked(&self, command: &mut std::process::Command) -> Result<CommandOutput> {
+        self.run(command)?.check()
+    }
+}
+
+///
+/// Runs real processes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &mut std::process::Command) -> Result<CommandOutput> {
+        command.run()
+    }
+}
+
+/// A scripted command of [FakeRunner].
+#[derive(Debug, Clone)]
+struct FakeCommand {
+    /// Program and args, only the program is matched if `any_args` is set.
+    words: Vec<String>,
+    any_args: bool,
+    code: i32,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    used: usize,
+}
+
+impl FakeCommand {
+    fn matches(&self, words: &[String]) -> bool {
+        if self.any_args {
+            words.first() == self.words.first()
+        } else {
+            words == self.words
+        }
+    }
+
+    fn pretty(&self) -> String {
+        let mut command = std::process::Command::new(&self.words[0]);
+        command.args(&self.words[1..]);
+        let mut pretty = command.to_string_pretty();
+        if self.any_args {
+            pretty.push_str(" ...");
+        }
+        pretty
+    }
+}
+
+///
+/// A [CommandRunner] that returns scripted output instead of running processes.
+///
+/// Commands are matched by program and args. If several scripted commands match,
+/// the first unused one is returned, then the last one is reused,
+/// so `on("a").exit(1).on("a")` fails the first time and succeeds afterwards.
+///
+/// A command that matches nothing returns an error listing the scripted commands.
+/// All commands run are recorded, see [FakeRunner::calls].
+#[derive(Debug, Clone, Default)]
+pub struct FakeRunner {
+    commands: Arc<Mutex<Vec<FakeCommand>>>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Script a command, it is split like a shell, see [shell_split].
+    ///
+    /// It succeeds with empty output, until changed by [FakeRunner::stdout], [FakeRunner::stderr] or [FakeRunner::exit].
+    ///
+    /// panics if the command is malformed or empty.
+    pub fn on(self, command: &str) -> Self {
+        let words = shell_split(command).expect("malformed fake command");
+        assert!(!words.is_empty(), "empty fake command");
+        self.push(words, false)
+    }
+
+    ///
+    /// Script a program that matches any args.
+    pub fn on_program(self, program: &str) -> Self {
+        self.push(vec![program.to_string()], true)
+    }
+
+    fn push(self, words: Vec<String>, any_args: bool) -> Self {
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(FakeCommand {
+                words,
+                any_args,
+                code: 0,
+                stdout: vec![],
+                stderr: vec![],
+                used: 0,
+            });
+        }
+        self
+    }
+
+    fn last(self, f: impl FnOnce(&mut FakeCommand)) -> Self {
+        if let Ok(mut commands) = self.commands.lock() {
+            f(commands
+                .last_mut()
+                .expect("call `on` before setting the output"));
+        }
+        self
+    }
+
+    /// Set the stdout of the last scripted command.
+    pub fn stdout(self, stdout: impl AsRef<[u8]>) -> Self {
+        self.last(|c| c.stdout = stdout.as_ref().to_vec())
+    }
+
+    /// Set the stderr of the last scripted command.
+    pub fn stderr(self, stderr: impl AsRef<[u8]>) -> Self {
+        self.last(|c| c.stderr = stderr.as_ref().to_vec())
+    }
+
+    /// Set the exit code of the last scripted command.
+    pub fn exit(self, code: i32) -> Self {
+        self.last(|c| c.code = code)
+    }
+
+    ///
+    /// The pretty command lines of all commands run, see [PrettyStringExt].
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    ///
+    /// Whether every scripted command has been run.
+    pub fn all_used(&self) -> bool {
+        self.commands
+            .lock()
+            .is_ok_and(|c| c.iter().all(|c| c.used > 0))
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn run(&self, command: &mut std::process::Command) -> Result<CommandOutput> {
+        let pretty = command.to_string_pretty();
+        self.calls.lock().newerr()?.push(pretty.clone());
+        let words: Vec<String> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|w| w.to_string_lossy().into_owned())
+            .collect();
+        let mut commands = self.commands.lock().newerr()?;
+        let matched = commands
+            .iter()
+            .position(|c| c.used == 0 && c.matches(&words))
+            .or_else(|| commands.iter().rposition(|c| c.matches(&words)));
+        let Some(i) = matched else {
+            let expected: Vec<String> = commands
+                .iter()
+                .map(|c| format!("  {}", c.pretty()))
+                .collect();
+            return Err(newerr!(
+                kind = NotFound,
+                "unexpected command `{pretty}`, expected one of:\n{}",
+                expected.join("\n")
+            ));
+        };
+        let fake = &mut commands[i];
+        fake.used += 1;
+        Ok(CommandOutput {
+            command: pretty,
+            status: exit_status(fake.code),
+            stdout: fake.stdout.clone(),
+            stderr: fake.stderr.clone(),
+            elapsed: Duration::ZERO,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn exit_status(_code: i32) -> ExitStatus {
+    ExitStatus::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ext::Command, prelude::ErrInfoExt};
+
+    #[test]
+    fn test_fake_runner() -> Result<()> {
+        let fake = FakeRunner::new()
+            .on("git push")
+            .exit(1)
+            .stderr("rejected\n")
+            .on("git push")
+            .on("git commit -m 'two words'")
+            .stdout("ok")
+            .on_program("ls");
+        let runner: &dyn CommandRunner = &fake;
+
+        let err = runner
+            .run_checked(&mut Command::from_str("git push")?)
+            .unwrap_err();
+        assert!(err.to_string().contains("exit code 1:\nrejected"));
+        assert!(
+            runner
+                .run_checked(&mut Command::from_str("git push")?)
+                .is_ok()
+        );
+        assert!(
+            runner
+                .run_checked(&mut Command::from_str("git push")?)
+                .is_ok()
+        );
+
+        let output = runner.run(&mut Command::with_args(
+            "git",
+            ["commit", "-m", "two words"],
+        ))?;
+        assert_eq!(output.stdout_lossy(), "ok");
+        assert!(runner.run(&mut Command::from_str("ls -l /")?)?.success());
+        assert!(fake.all_used());
+
+        let err = runner.run(&mut Command::from_str("rm -rf /")?).unwrap_err();
+        assert!(err.is_kind(crate::prelude::ErrorKind::NotFound));
+        assert!(
+            err.to_string()
+                .starts_with("unexpected command `rm -rf /`, expected one of:\n  git push\n")
+        );
+        assert!(
+            err.to_string()
+                .contains("\n  git commit -m 'two words'\n  ls ...")
+        );
+
+        assert_eq!(fake.calls().len(), 6);
+        assert_eq!(fake.calls()[3], "git commit -m 'two words'");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_system_runner() -> Result<()> {
+        let output = SystemRunner.run_checked(&mut Command::from_str("echo hi")?)?;
+        assert_eq!(output.stdout_lossy(), "hi\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    newerr,
+    prelude::{Err, Result},
+};
+
+/// The extensions tried on Windows when `PATHEXT` is not set.
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+///
+/// Find the executable of a program, like the `which` command.
+///
+/// - A program containing a path separator is checked as is, relative to the current directory.
+/// - Otherwise each directory of `PATH` is searched in order.
+/// - On Windows the extensions in `PATHEXT` are also tried, e.g. `git` finds `git.exe`.
+///
+/// Only executable files are returned. The error is [crate::prelude::ErrorKind::NotFound]
+/// and lists the searched directories.
+///
+/// # example
+/// ```
+/// use libcommon::ext::which;
+///
+/// # #[cfg(unix)]
+/// assert!(which("sh").unwrap().is_absolute());
+/// assert!(which("not-exists-command").is_err());
+/// ```
+pub fn which(program: impl AsRef<OsStr>) -> Result<PathBuf> {
+    let pathext = if cfg!(windows) {
+        Some(std::env::var_os("PATHEXT").unwrap_or_else(|| DEFAULT_PATHEXT.into()))
+    } else {
+        None
+    };
+    which_in(program, std::env::var_os("PATH"), pathext)
+}
+
+///
+/// Same as [which], but searches the given `PATH`, and tries the extensions in `pathext` if given.
+pub fn which_in(
+    program: impl AsRef<OsStr>,
+    path: Option<impl AsRef<OsStr>>,
+    pathext: Option<impl AsRef<OsStr>>,
+) -> Result<PathBuf> {
+    let program = program.as_ref();
+    if program.is_empty() {
+        return Err(newerr!(kind = InvalidInput, "empty program"));
+    }
+    let exts: Vec<OsString> = pathext
+        .map(|exts| {
+            exts.as_ref()
+                .to_string_lossy()
+                .split(';')
+                .filter(|e| !e.is_empty())
+                .map(OsString::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    let has_separator = Path::new(program).components().count() > 1
+        || program.to_string_lossy().contains(std::path::is_separator);
+    if has_separator {
+        return candidates(Path::new(program), &exts)
+            .find(|p| is_executable(p))
+            .ok_or_else(|| {
+                newerr!(
+                    kind = NotFound,
+                    "program `{}` not found or not executable",
+                    program.to_string_lossy()
+                )
+            });
+    }
+    let dirs: Vec<PathBuf> = path
+        .map(|p| std::env::split_paths(p.as_ref()).collect())
+        .unwrap_or_default();
+    dirs.iter()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| candidates(&dir.join(program), &exts).collect::<Vec<_>>())
+        .find(|p| is_executable(p))
+        .ok_or_else(|| {
+            let searched: Vec<String> = dirs.iter().map(|d| format!("  {}", d.display())).collect();
+            newerr!(
+                kind = NotFound,
+                "program `{}` not found in PATH, searched:\n{}",
+                program.to_string_lossy(),
+                searched.join("\n")
+            )
+        })
+}
+
+///
+/// The source of the error of a failed spawn.
+///
+/// A missing program is reported with the searched directories, see [which].
+pub(crate) fn spawn_error(program: &OsStr, e: std::io::Error) -> Err {
+    if e.kind() == std::io::ErrorKind::NotFound
+        && let Err(missing) = which(program)
+    {
+        return missing;
+    }
+    e.into()
+}
+
+/// The path itself, then the path with each extension appended.
+fn candidates<'a>(path: &'a Path, exts: &'a [OsString]) -> impl Iterator<Item = PathBuf> + 'a {
+    std::iter::once(path.to_path_buf()).chain(exts.iter().map(move |ext| {
+        let mut p = path.as_os_str().to_owned();
+        p.push(ext);
+        PathBuf::from(p)
+    }))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ext::Command, prelude::ErrInfoExt, prelude::ErrorKind};
+
+    #[test]
+    #[cfg(unix)]
+    fn test_which() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("libcommon_which_{}", std::process::id()));
+        let bin = dir.join("bin");
+        std::fs::create_dir_all(&bin)?;
+        let tool = bin.join("tool");
+        std::fs::write(&tool, "#!/bin/sh\n")?;
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755))?;
+        let data = bin.join("data");
+        std::fs::write(&data, "")?;
+        let cmd = bin.join("run.cmd");
+        std::fs::write(&cmd, "")?;
+        std::fs::set_permissions(&cmd, std::fs::Permissions::from_mode(0o755))?;
+
+        let path = std::env::join_paths([dir.join("missing"), bin.clone()])?;
+        assert_eq!(which_in("tool", Some(&path), None::<&str>)?, tool);
+        assert_eq!(which_in(&tool, None::<&str>, None::<&str>)?, tool);
+        assert_eq!(which_in("run", Some(&path), Some(".EXE;.cmd"))?, cmd);
+
+        let err = which_in("data", Some(&path), None::<&str>).unwrap_err();
+        assert!(err.is_kind(ErrorKind::NotFound));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "program `data` not found in PATH, searched:\n  {}\n  {}",
+                dir.join("missing").display(),
+                bin.display()
+            )
+        );
+        assert!(which_in(&data, None::<&str>, None::<&str>).is_err());
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(Command::exists("sh"));
+        assert!(!Command::exists("not-exists-command"));
+
+        // Only the resolved constructors check the host
+        assert!(Command::from_str("not-exists-command -v").is_ok());
+        let err = Command::from_str_resolved("not-exists-command -v").unwrap_err();
+        assert!(err.is_kind(ErrorKind::NotFound));
+        assert!(
+            err.to_string()
+                .starts_with("program `not-exists-command` not found in PATH")
+        );
+        let cmd = Command::from_args_resolved(&["sh", "-c", "true"])?;
+        assert_eq!(Path::new(cmd.get_program()), which("sh")?);
+        Ok(())
+    }
+}
@@ -1,7 +1,26 @@
 mod command;
+mod command_async;
+#[cfg(target_os = "linux")]
+mod command_group;
+mod command_log;
+mod command_pipe;
+mod command_run;
+mod command_runner;
+mod command_which;
 mod file;
 mod str;
 
 pub use command::*;
+pub use command_log::*;
+pub use command_pipe::*;
+pub use command_run::*;
+pub use command_runner::*;
+pub use command_which::*;
 pub use file::*;
 pub use str::*;
+
+#[cfg(feature = "tokio")]
+pub use command_async::*;
+
+#[cfg(target_os = "linux")]
+pub use command_group::*;
@@ -1,19 +1,20 @@
 use std::process::Command;
 
+use crate::ext::shell_quote;
+
 /// 将某些对象转为更加可读的形式
 pub trait PrettyStringExt {
     fn to_string_pretty(&self) -> String;
 }
 
 impl PrettyStringExt for Command {
+    /// 参数会按需加上引号，输出可以通过[crate::ext::Command::from_str]还原
     fn to_string_pretty(&self) -> String {
-        let program = self.get_program().to_string_lossy();
-        let args: Vec<String> = self
-            .get_args()
-            .map(|arg| arg.to_string_lossy().into_owned())
-            .collect();
-
-        format!("{} {}", program, args.join(" "))
+        std::iter::once(self.get_program())
+            .chain(self.get_args())
+            .map(|arg| shell_quote(&arg.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 }
 
@@ -29,8 +30,15 @@ mod tests {
     fn test_command() {
         log_setup();
         let mut cmd = Command::new("ls");
-        cmd.args(&["-l", "-a"]);
+        cmd.args(["-l", "-a"]);
         info!("{}", cmd.to_string_pretty());
         assert_eq!(cmd.to_string_pretty(), "ls -l -a");
+
+        let mut cmd = Command::new("git");
+        cmd.args(["commit", "-m", "it's done", ""]);
+        let pretty = cmd.to_string_pretty();
+        assert_eq!(pretty, r#"git commit -m 'it'\''s done' ''"#);
+        let parsed = crate::ext::Command::from_str(&pretty).unwrap();
+        assert!(parsed.get_args().eq(cmd.get_args()));
     }
 }
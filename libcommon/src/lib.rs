@@ -6,13 +6,19 @@ pub mod log;
 ///
 /// 包括通用[crate::prelude::Err]和[crate::prelude::Result]类型，以及日志[crate::log]相关
 pub mod prelude;
+/// 失败重试
+pub mod retry;
 
 mod macroext;
 
+// 使过程宏生成的`libcommon::`路径在本crate中也可以使用
+extern crate self as libcommon;
+
 pub use log::{log_setup, log_setup_result};
 pub use macro_builder::{Builder, Default_With, Getter, With};
 pub use macro_log::logsetup;
 pub use macro_logiferr::logiferr;
+pub use macro_retry::retry;
 pub use macro_timer::timer;
 
 #[cfg(test)]
@@ -24,6 +30,7 @@ mod tests {
     #[test]
     fn test_macro() {
         let _ = macro_result();
+        assert!(io::macro_io_result().is_err());
     }
 
     #[logiferr]
@@ -32,6 +39,17 @@ mod tests {
         Err(newerr!("test macro result str"))
     }
 
+    mod io {
+        use crate::{logiferr, prelude::error};
+
+        type Result<T> = std::result::Result<T, std::io::Error>;
+
+        #[logiferr]
+        pub(super) fn macro_io_result() -> Result<()> {
+            Err(std::io::Error::other("test macro io error"))
+        }
+    }
+
     #[test]
     fn test_macro_builder() {
         let user = UserBuilder::new("Jack".to_string(), 22)
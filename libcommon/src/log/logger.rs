@@ -1,8 +1,12 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use colored::{Color, Colorize};
+use log::LevelFilter;
 
-use crate::log::logwriter;
+use crate::log::{logwriter, recorder, redact, sample, stats};
 use crate::newerr;
 use crate::prelude::Result;
 
@@ -10,11 +14,20 @@ struct Logger;
 
 impl log::Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= log::Level::max()
+        metadata.level() <= log_level()
     }
 
     fn log(&self, record: &log::Record) {
-        if !self.enabled(record.metadata()) {
+        let skip = skip_reason(self.enabled(record.metadata()), || {
+            sample::sampled_out(record.metadata())
+        });
+        match skip {
+            Some(Skip::Filtered) => stats::inc_filtered(),
+            Some(Skip::Sampled) => stats::inc_sampled(),
+            None => {}
+        }
+        // 开启飞行记录时，未输出的日志也需要记录；否则在格式化之前返回，几乎没有开销
+        if skip.is_some() && !recorder::is_enabled() {
             return;
         }
         let (level, color) = match record.level() {
@@ -25,18 +38,35 @@ impl log::Log for Logger {
             log::Level::Trace => ("TRACT", Color::White),
         };
         let is_record = record.target() == "log:record";
-        let mut str = if is_record {
-            format!("{}", record.args())
-        } else {
-            format!("{}: {}", level, record.args())
+        let line = |msg: String| {
+            if is_record {
+                return msg;
+            }
+            match (record.file(), record.line()) {
+                (Some(f), Some(l)) => format!("{level}: {msg}    ===> ({f}:{l})"),
+                _ => format!("{level}: {msg}"),
+            }
         };
-        if !is_record && let (Some(f), Some(l)) = (record.file(), record.line()) {
-            str = format!("{str}    ===> ({f}:{l})");
+        let msg = record.args().to_string();
+        // 飞行记录保存未脱敏的内容，输出飞行记录时再脱敏，避免每条日志都执行脱敏规则
+        if skip.is_some() {
+            recorder::record(line(msg));
+            return;
+        }
+        if recorder::is_enabled() {
+            recorder::record(line(msg.clone()));
         }
+        // 在输出到任何位置之前脱敏
+        let str = line(redact::redact(msg));
+        stats::inc_record(record.level());
         println!("{}", str.color(color));
-        if let Err(e) = logwriter::write(str) {
+        if let Err(e) = logwriter::write_record(record, str) {
+            stats::inc_unsent();
             eprintln!("log write failed {e:?}");
         }
+        if record.level() == log::Level::Error {
+            recorder::dump_on_error();
+        }
     }
 
     fn flush(&self) {
@@ -44,8 +74,34 @@ impl log::Log for Logger {
     }
 }
 
+/// 日志未输出的原因
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Skip {
+    /// 低于输出级别
+    Filtered,
+    /// 被采样丢弃
+    Sampled,
+}
+
+/// 先判断输出级别，只对达到输出级别的日志采样，低于输出级别的日志不影响采样计数
+fn skip_reason(enabled: bool, sampled_out: impl FnOnce() -> bool) -> Option<Skip> {
+    if !enabled {
+        Some(Skip::Filtered)
+    } else if sampled_out() {
+        Some(Skip::Sampled)
+    } else {
+        None
+    }
+}
+
 static LOGGER: Logger = Logger;
 
+/// 直接交给[Logger]处理，用于转发其它来源的日志
+#[allow(unused)]
+pub(crate) fn log_record(record: &log::Record) {
+    log::Log::log(&LOGGER, record);
+}
+
 /// 初始化日志
 ///
 /// 如果已经初始化过日志会报错，忽略这个报错
@@ -76,15 +132,62 @@ pub fn log_setup_result() -> Result<()> {
 }
 
 pub fn log_set_level(level: &str) {
-    log::set_max_level(log::LevelFilter::from_str(level).expect("must be level str"));
+    set_level(LevelFilter::from_str(level).expect("must be level str"));
+}
+
+/**
+ * 日志输出级别
+ *
+ * 与[log::max_level]分开保存：开启飞行记录时[log::max_level]需要为`Trace`，以便记录所有日志
+ */
+static LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Trace as usize);
+
+/// 当前的日志输出级别
+pub(crate) fn log_level() -> LevelFilter {
+    let level = LEVEL.load(Ordering::Relaxed);
+    LevelFilter::iter().nth(level).unwrap_or(LevelFilter::Trace)
+}
+
+fn set_level(level: LevelFilter) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+    update_max_level();
+}
+
+/// 根据输出级别和飞行记录重新设置[log::max_level]
+pub(crate) fn update_max_level() {
+    let max = if recorder::is_enabled() {
+        LevelFilter::Trace
+    } else {
+        log_level()
+    };
+    log::set_max_level(max);
 }
 
 #[cfg(debug_assertions)]
 fn _log_setup_level() {
-    log::set_max_level(log::LevelFilter::Trace);
+    set_level(LevelFilter::Trace);
 }
 
 #[cfg(not(debug_assertions))]
 fn _log_setup_level() {
-    log::set_max_level(log::LevelFilter::Info);
+    set_level(LevelFilter::Info);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_reason() {
+        let called = std::cell::Cell::new(false);
+        let sampled_out = || {
+            called.set(true);
+            true
+        };
+        assert_eq!(skip_reason(false, sampled_out), Some(Skip::Filtered));
+        assert!(!called.get());
+        assert_eq!(skip_reason(true, sampled_out), Some(Skip::Sampled));
+        assert!(called.get());
+        assert_eq!(skip_reason(true, || false), None);
+    }
 }
@@ -0,0 +1,202 @@
+use crate::if_feature;
+
+if_feature!("tracing" =>
+    use std::fmt::{Debug, Write};
+
+    use tracing::{
+        Event, Subscriber,
+        field::{Field, Visit},
+        span,
+    };
+    use tracing_subscriber::{Layer, layer::Context, prelude::*, registry::LookupSpan};
+
+    use crate::{log::logger, newerr, prelude::Result};
+
+    ///
+    /// 将`tracing`的事件转发到[crate::log]，与`log`的日志使用相同的格式、过滤和文件写入
+    ///
+    /// 事件所在的span名称和字段会作为前缀输出，如`request{id=1}:db: query user=a`
+    ///
+    /// # example
+    ///
+    /// ```ignore
+    /// use tracing_subscriber::prelude::*;
+    ///
+    /// log_setup();
+    /// tracing_subscriber::registry().with(LogLayer).init();
+    /// ```
+    pub struct LogLayer;
+
+    /// span上记录的字段
+    struct SpanFields(String);
+
+    /// 收集事件的`message`和其余字段
+    #[derive(Default)]
+    struct FieldVisitor {
+        message: String,
+        fields: String,
+    }
+
+    impl Visit for FieldVisitor {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                self.message = value.to_string();
+            } else {
+                let _ = write!(self.fields, " {}={value}", field.name());
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() == "message" {
+                self.message = format!("{value:?}");
+            } else {
+                let _ = write!(self.fields, " {}={value:?}", field.name());
+            }
+        }
+    }
+
+    fn to_log_level(level: tracing::Level) -> log::Level {
+        match level {
+            tracing::Level::ERROR => log::Level::Error,
+            tracing::Level::WARN => log::Level::Warn,
+            tracing::Level::INFO => log::Level::Info,
+            tracing::Level::DEBUG => log::Level::Debug,
+            tracing::Level::TRACE => log::Level::Trace,
+        }
+    }
+
+    impl<S> Layer<S> for LogLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor::default();
+            attrs.record(&mut visitor);
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(SpanFields(visitor.fields));
+            }
+        }
+
+        fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor::default();
+            values.record(&mut visitor);
+            if let Some(span) = ctx.span(id)
+                && let Some(fields) = span.extensions_mut().get_mut::<SpanFields>()
+            {
+                fields.0.push_str(&visitor.fields);
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let Some(msg) = format_event(event, &ctx, log::max_level()) else {
+                return;
+            };
+            let meta = event.metadata();
+            logger::log_record(
+                &log::Record::builder()
+                    .args(format_args!("{msg}"))
+                    .level(to_log_level(*meta.level()))
+                    .target(meta.target())
+                    .module_path(meta.module_path())
+                    .file(meta.file())
+                    .line(meta.line())
+                    .build(),
+            );
+        }
+    }
+
+    /// 生成事件的日志内容，低于`max_level`时在格式化之前返回`None`
+    fn format_event<S>(
+        event: &Event<'_>,
+        ctx: &Context<'_, S>,
+        max_level: log::LevelFilter,
+    ) -> Option<String>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if to_log_level(*event.metadata().level()) > max_level {
+            return None;
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        let mut msg = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                msg.push_str(span.name());
+                if let Some(fields) = span.extensions().get::<SpanFields>()
+                    && !fields.0.is_empty()
+                {
+                    let _ = write!(msg, "{{{}}}", fields.0.trim_start());
+                }
+                msg.push(':');
+            }
+            msg.push(' ');
+        }
+        msg.push_str(&visitor.message);
+        msg.push_str(&visitor.fields);
+        Some(msg)
+    }
+
+    ///
+    /// 将[LogLayer]设置为`tracing`的全局订阅者
+    ///
+    /// 需要先初始化日志；如果已经设置过全局订阅者会返回错误
+    pub fn log_setup_tracing() -> Result<()> {
+        let subscriber = tracing_subscriber::registry().with(LogLayer);
+        tracing::subscriber::set_global_default(subscriber)
+            .map_err(|e| newerr!("tracing setup failed {:?}", e))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::{Arc, Mutex};
+
+        use super::*;
+        use crate::log::log_setup;
+
+        /// 按照给定的输出级别保存[format_event]的结果，未输出时保存`None`
+        struct Capture(log::LevelFilter, Arc<Mutex<Vec<Option<String>>>>);
+
+        impl<S> Layer<S> for Capture
+        where
+            S: Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+                let msg = format_event(event, &ctx, self.0);
+                self.1.lock().unwrap().push(msg);
+            }
+        }
+
+        #[test]
+        fn test_tracing() {
+            log_setup();
+            let lines = Arc::new(Mutex::new(Vec::new()));
+            // span的字段由LogLayer保存
+            let subscriber = tracing_subscriber::registry()
+                .with(LogLayer)
+                .with(Capture(log::LevelFilter::Info, lines.clone()));
+            tracing::subscriber::with_default(subscriber, || {
+                let span = tracing::info_span!("request", id = 1);
+                let _enter = span.enter();
+                tracing::info!(user = "a", "test tracing info");
+                tracing::debug_span!("db").in_scope(|| {
+                    tracing::warn!(user = "a", "test tracing warn");
+                    tracing::debug!("test tracing debug");
+                });
+            });
+            let subscriber =
+                tracing_subscriber::registry().with(Capture(log::LevelFilter::Info, lines.clone()));
+            tracing::subscriber::with_default(subscriber, || tracing::info!("no span"));
+            assert_eq!(
+                *lines.lock().unwrap(),
+                vec![
+                    Some("request{id=1}: test tracing info user=a".to_string()),
+                    Some("request{id=1}:db: test tracing warn user=a".to_string()),
+                    None,
+                    Some("no span".to_string()),
+                ]
+            );
+        }
+    }
+);
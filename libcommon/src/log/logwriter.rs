@@ -17,29 +17,71 @@ if_feature!(not("logfile") =>
         Ok(())
     }
 
+    /**
+     * 日志数据传递，根据日志的target和模块写入对应的文件
+     */
+    pub(crate) fn write_record<S: Into<String>>(_: &log::Record, _: S) -> Result<()> {
+        Ok(())
+    }
+
     pub(crate) fn flush() {
     }
 
+    /**
+     * 等待写入的日志数量
+     */
+    pub(crate) fn queue_depth() -> usize {
+        0
+    }
+
 );
 
 if_feature!("logfile" =>
     use std::{
+        fs::OpenOptions,
+        io::ErrorKind as IoErrorKind,
         path::{Path, PathBuf},
         sync::{
-            OnceLock, RwLock,
+            Arc, Mutex, OnceLock, RwLock,
             atomic::{AtomicUsize, Ordering},
         },
     };
 
-    use crate::{ext::WriteAppendExt, log::log_setup_result, prelude::*};
+    use crate::{
+        ext::WriteAppendExt,
+        log::{
+            FailurePolicy, LogHeader, log_setup_result, logwriter_failure::WriteFailure, stats,
+        },
+        prelude::*,
+    };
     use crossbeam_channel::{Receiver, Sender, bounded};
+    use macro_builder::With;
 
     /**
      * 日志数据传递
      */
     pub(crate) fn write<S: Into<String>>(s: S) -> Result<()> {
+        send(LogLine {
+            target: String::new(),
+            module: String::new(),
+            line: s.into(),
+        })
+    }
+
+    /**
+     * 日志数据传递，根据日志的target和模块写入对应的文件
+     */
+    pub(crate) fn write_record<S: Into<String>>(record: &log::Record, s: S) -> Result<()> {
+        send(LogLine {
+            target: record.target().to_string(),
+            module: record.module_path().unwrap_or_default().to_string(),
+            line: s.into(),
+        })
+    }
+
+    fn send(line: LogLine) -> Result<()> {
         if let Some(tx) = LOG_SENDER.get() {
-            tx.send(s.into())?;
+            tx.send(line)?;
         }
         Ok(())
     }
@@ -51,6 +93,13 @@ if_feature!("logfile" =>
         let _ = write("");
     }
 
+    /**
+     * 等待写入的日志数量
+     */
+    pub(crate) fn queue_depth() -> usize {
+        LOG_SENDER.get().map(|tx| tx.len()).unwrap_or(0)
+    }
+
     /**
      * 用于收集日志写入文件的线程
      * 会占用该线程
@@ -71,21 +120,139 @@ if_feature!("logfile" =>
     /// 并将日志写入文件
     /// dir: 日志文件目录，日志文件会自动分文件存储
     ///     每次打开会创建新文件；每一份文件超过最大值会自动分文件存储
+    ///     文件名是原子占用的，多个进程共用同一个目录时也不会写入同一个文件，
+    ///     但文件名无法区分进程，需要区分时使用[log_setup_with_config]并设置[LogWriterConfig::per_process]
     ///
     pub fn log_setup_with_writer<P: AsRef<Path>>(executor: &impl LogWriterTask, dir: P) {
+        log_setup_with_config(executor, LogWriterConfig::new(dir));
+    }
+
+    ///
+    /// 初始化日志显示
+    /// 并按照配置将日志写入文件
+    ///
+    /// # example
+    ///
+    /// ```ignore
+    /// // db.*的日志写入db_*.txt，http的日志写入access_*.txt，其余写入log_*.txt
+    /// let config = LogWriterConfig::new("./log")
+    ///     .route("db.*", "db")
+    ///     .route("http", "access");
+    /// log_setup_with_config(&LogWriterDefaultTask, config);
+    /// ```
+    pub fn log_setup_with_config(executor: &impl LogWriterTask, config: LogWriterConfig) {
         let result = log_setup_result();
         // 已经设置过
         if result.is_err() {
             return;
         }
 
-        let (tx, rx) = bounded::<String>(5);
+        let (tx, rx) = bounded::<LogLine>(5);
 
         // 失败说明已经设置了
         let _ = LOG_SENDER.set(tx);
 
-        let runner = LogRunner::new(dir.as_ref().to_path_buf(), rx);
-        executor.spawn(async move { runner.run() });
+        let router = LogRouter::new(config, rx);
+        executor.spawn(async move { router.run() });
+    }
+
+    /**
+     * 日志文件配置
+     */
+    #[derive(With)]
+    pub struct LogWriterConfig {
+        /**
+         * 日志文件目录
+         */
+        dir: PathBuf,
+        /**
+         * 单个日志文件最大大小
+         */
+        max_size: usize,
+        /**
+         * 按照target或者模块写入单独文件的规则，按添加顺序匹配
+         */
+        #[with(skip)]
+        routes: Vec<LogRoute>,
+        /**
+         * 实例标识，会加入文件名中
+         *
+         * 多个进程使用同一个目录时，可以设置不同的标识以区分各进程的文件，见[LogWriterConfig::per_process]
+         */
+        instance: Option<String>,
+        /**
+         * 每个日志文件开始时写入的头部信息
+         */
+        header: Option<LogHeader>,
+        /**
+         * 写入失败时的处理策略
+         */
+        failure: FailurePolicy,
+    }
+
+    impl LogWriterConfig {
+        pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+            Self {
+                dir: dir.as_ref().to_path_buf(),
+                max_size: 1024 * 1024 * 5,
+                routes: vec![],
+                instance: None,
+                header: None,
+                failure: FailurePolicy::default(),
+            }
+        }
+
+        ///
+        /// 使用进程id作为实例标识，文件名如`log_12345_%Y%m%d%H%M.txt`
+        ///
+        /// 每个进程写入自己的文件并独立轮转
+        pub fn per_process(self) -> Self {
+            self.with_instance(std::process::id().to_string())
+        }
+
+        ///
+        /// 将target或者模块匹配`prefix`的日志写入以`name`开头的单独文件
+        ///
+        /// `prefix`: 如`db`、`db.*`、`app::http`，结尾的`*`会被忽略
+        /// `name`: 文件名前缀，如`db`会写入`db_%Y%m%d%H%M.txt`
+        pub fn route(mut self, prefix: impl Into<String>, name: impl Into<String>) -> Self {
+            let prefix: String = prefix.into();
+            self.routes.push(LogRoute {
+                prefix: prefix.trim_end_matches('*').to_string(),
+                name: name.into(),
+            });
+            self
+        }
+    }
+
+    struct LogRoute {
+        prefix: String,
+        name: String,
+    }
+
+    impl LogRoute {
+        fn matches(&self, line: &LogLine) -> bool {
+            [&line.target, &line.module].iter().any(|s| {
+                s.strip_prefix(&self.prefix).is_some_and(|rest| {
+                    // 避免`db`匹配到`dbx`
+                    rest.is_empty()
+                        || self.prefix.ends_with(['.', ':'])
+                        || rest.starts_with(['.', ':'])
+                })
+            })
+        }
+    }
+
+    /**
+     * 传递给写入线程的日志
+     */
+    pub(crate) struct LogLine {
+        /**
+         * 日志的target和所在模块，用于匹配[LogRoute]
+         */
+        target: String,
+        module: String,
+        line: String,
     }
 
     /**
@@ -93,17 +260,107 @@ if_feature!("logfile" =>
      * 优点是之后可以进行无锁读取
      * 缺点是初始化后无法对值进行修改
      */
-    static LOG_SENDER: OnceLock<Sender<String>> = OnceLock::new();
+    static LOG_SENDER: OnceLock<Sender<LogLine>> = OnceLock::new();
+
+    /**
+     * 接收日志并分发给对应的[LogRunner]
+     */
+    struct LogRouter {
+        /**
+         * 日志数据接收
+         */
+        rx: Receiver<LogLine>,
+        routes: Vec<(LogRoute, LogRunner)>,
+        /**
+         * 未匹配任何规则的日志
+         */
+        default: LogRunner,
+    }
+
+    impl LogRouter {
+        fn new(config: LogWriterConfig, rx: Receiver<LogLine>) -> Self {
+            let LogWriterConfig {
+                dir,
+                max_size,
+                routes,
+                instance,
+                header,
+                failure,
+            } = config;
+            let header = header.map(Arc::new);
+            let failure = Arc::new(failure);
+            let file_name = |name: &str| match &instance {
+                Some(instance) => format!("{name}_{instance}"),
+                None => name.to_string(),
+            };
+            let routes = routes
+                .into_iter()
+                .map(|r| {
+                    let runner = LogRunner::new(
+                        dir.clone(),
+                        &file_name(&r.name),
+                        max_size,
+                        header.clone(),
+                        failure.clone(),
+                    );
+                    (r, runner)
+                })
+                .collect();
+            Self {
+                rx,
+                routes,
+                default: LogRunner::new(dir, &file_name("log"), max_size, header, failure),
+            }
+        }
 
+        fn run(&self) {
+            loop {
+                match self.rx.recv() {
+                    Ok(l) => {
+                        // 空白字符作为退出机制 // 日志因为附加信息的存在，正常消息不可能是空白字符
+                        if l.line.is_empty() {
+                            let runners = self.routes.iter().map(|(_, runner)| runner);
+                            for runner in runners.chain(std::iter::once(&self.default)) {
+                                if let Err(e) = runner.flush_pending() {
+                                    stats::write_error(&e);
+                                    eprintln!("write log error: {e}");
+                                }
+                            }
+                            break;
+                        }
+                        let runner = self
+                            .routes
+                            .iter()
+                            .find(|(r, _)| r.matches(&l))
+                            .map(|(_, runner)| runner)
+                            .unwrap_or(&self.default);
+                        let res = runner.write(l.line);
+                        if let Err(e) = res {
+                            stats::write_error(&e);
+                            eprintln!("write log error: {e}");
+                        }
+                    }
+                    Err(e) => {
+                        println!("log runner exit: {e}");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * 写入一组轮转的日志文件
+     */
     struct LogRunner {
         /**
          * 日志文件目录
          */
         dir: RwLock<PathBuf>,
         /**
-         * 日志数据接收
+         * 日志文件名前缀
          */
-        rx: RwLock<Receiver<String>>,
+        name: String,
         /**
          * 日志文件最大大小
          */
@@ -116,43 +373,104 @@ if_feature!("logfile" =>
          * 当前文件大小
          */
         curr_size: AtomicUsize,
+        /**
+         * 新文件的头部信息
+         */
+        header: Option<Arc<LogHeader>>,
+        /**
+         * 写入失败时的处理策略
+         */
+        policy: Arc<FailurePolicy>,
+        /**
+         * 写入失败的状态，写入正常时为[None]
+         */
+        failure: Mutex<Option<WriteFailure>>,
     }
 
     impl LogRunner {
-        pub(crate) fn new(p: PathBuf, rx: Receiver<String>) -> Self {
+        pub(crate) fn new(
+            p: PathBuf,
+            name: &str,
+            max_size: usize,
+            header: Option<Arc<LogHeader>>,
+            policy: Arc<FailurePolicy>,
+        ) -> Self {
             Self {
                 dir: RwLock::new(p),
-                rx: RwLock::new(rx),
-                max_size: 1024 * 1024 * 5,
+                name: name.to_string(),
+                max_size,
                 curr_file: RwLock::new(None),
                 curr_size: AtomicUsize::new(0),
+                header,
+                policy,
+                failure: Mutex::new(None),
             }
         }
 
-        pub(crate) fn run(&self) {
-            if let Ok(rx) = self.rx.read() {
-                loop {
-                    match rx.recv() {
-                        Ok(s) => {
-                            // 空白字符作为退出机制 // 日志因为附加信息的存在，正常消息不可能是空白字符
-                            if s.is_empty() {
-                                break;
-                            }
-                            let res = self.write(s);
-                            if let Err(e) = res {
-                                eprintln!("write log error: {e}");
-                            }
-                        }
-                        Err(e) => {
-                            println!("log runner exit: {e}");
-                            break;
-                        }
-                    }
+        ///
+        /// 写入日志，失败时按照[FailurePolicy]处理
+        ///
+        /// 只有实际尝试写入并失败时才返回错误，等待重试期间的日志会被保留
+        fn write(&self, s: String) -> Result<()> {
+            let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let line = format!("{time}  {s}\n");
+
+            let mut failure = self.failure.lock().newerr()?;
+            if let Some(f) = failure.as_mut() {
+                if !f.can_retry() {
+                    f.hold(&self.policy, &self.name, line);
+                    return Ok(());
                 }
+                if let Err(e) = self.recover(f) {
+                    f.failed(&self.policy);
+                    f.hold(&self.policy, &self.name, line);
+                    return Err(e);
+                }
+                *failure = None;
+            }
+            if let Err(e) = self.append(&line) {
+                let f = failure.get_or_insert_with(WriteFailure::new);
+                f.failed(&self.policy);
+                f.hold(&self.policy, &self.name, line);
+                return Err(e);
             }
+            Ok(())
         }
 
-        fn write(&self, s: String) -> Result<()> {
+        ///
+        /// 退出前不等待退避时间，尝试写入一次保留的日志
+        ///
+        /// 失败时保留的日志仍然在备用目录或者stderr中
+        fn flush_pending(&self) -> Result<()> {
+            let mut failure = self.failure.lock().newerr()?;
+            if let Some(f) = failure.as_mut() {
+                if let Err(e) = self.recover(f) {
+                    f.failed(&self.policy);
+                    return Err(e);
+                }
+                *failure = None;
+            }
+            Ok(())
+        }
+
+        /**
+         * 写入恢复说明和保留的日志
+         *
+         * 恢复说明只写入一次，写入部分日志后再次失败时，下次恢复只写入剩余的日志
+         */
+        fn recover(&self, f: &mut WriteFailure) -> Result<()> {
+            if !f.notified {
+                self.append(&f.recovery_notice())?;
+                f.notified = true;
+            }
+            while let Some(line) = f.pending.front() {
+                self.append(line)?;
+                f.pending.pop_front();
+            }
+            Ok(())
+        }
+
+        fn append(&self, s: &str) -> Result<()> {
             let mut need_new_file = false;
             let mut curr_size = self.curr_size.load(Ordering::Relaxed);
             let mut file_path: Option<PathBuf> = None;
@@ -174,30 +492,167 @@ if_feature!("logfile" =>
             }
 
             if need_new_file {
-                let new_file = self.new_file()?;
-                let mut currlock = self.curr_file.write().newerr()?;
-                *currlock = Some(new_file.clone());
-                file_path = Some(new_file.clone());
+                let mut new_file = self.new_file()?;
+                let prev = {
+                    let mut currlock = self.curr_file.write().newerr()?;
+                    currlock.replace(new_file.clone())
+                };
                 curr_size = 0;
+                if prev.is_some() {
+                    stats::inc_rotation();
+                }
+                if let Some(header) = &self.header {
+                    let header = header.render(prev.as_deref());
+                    new_file.write_append(header.as_bytes())?;
+                    stats::add_bytes(header.len());
+                    curr_size = header.len();
+                }
+                file_path = Some(new_file);
             }
 
             if let Some(mut path) = file_path {
-                let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-                let new_s = format!("{time}  {s}\n");
                 // IO操作时不持有锁
-                path.write_append(new_s.as_bytes())?;
+                path.write_append(s.as_bytes())?;
+                stats::add_bytes(s.len());
 
                 self.curr_size.store(curr_size + s.len(), Ordering::Relaxed);
             }
             Ok(())
         }
 
+        ///
+        /// 创建新的日志文件
+        ///
+        /// 同一分钟内重新打开或者轮转时，不追加到已有的文件；
+        /// 使用`create_new`原子地占用文件名，多个进程同时创建时也不会写入同一个文件
         fn new_file(&self) -> Result<PathBuf> {
             let path = self.dir.read().newerr()?.clone();
-            let name = chrono::Local::now()
-                .format("log_%Y%m%d%H%M.txt")
-                .to_string();
-            Ok(path.join(name))
+            std::fs::create_dir_all(&path)?;
+            let time = chrono::Local::now().format("%Y%m%d%H%M");
+            let mut index = 0;
+            loop {
+                let file = match index {
+                    0 => path.join(format!("{}_{time}.txt", self.name)),
+                    _ => path.join(format!("{}_{time}_{index}.txt", self.name)),
+                };
+                match OpenOptions::new().write(true).create_new(true).open(&file) {
+                    Ok(_) => return Ok(file),
+                    Err(e) if e.kind() == IoErrorKind::AlreadyExists => index += 1,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_route() {
+            let config = LogWriterConfig::new("log")
+                .route("db.*", "db")
+                .route("http", "access");
+            let line = |target: &str, module: &str| LogLine {
+                target: target.to_string(),
+                module: module.to_string(),
+                line: "a".to_string(),
+            };
+            let (db, http) = (&config.routes[0], &config.routes[1]);
+            assert!(db.matches(&line("db.query", "app")));
+            assert!(!db.matches(&line("db", "app")));
+            assert!(http.matches(&line("http", "app")));
+            assert!(http.matches(&line("app", "http::server")));
+            assert!(!http.matches(&line("https", "app")));
+        }
+
+        #[test]
+        fn test_new_file() -> Result<()> {
+            let dir = crate::curr_dir!("test_new_file")?;
+            let config = LogWriterConfig::new(&dir).per_process();
+            let (_, rx) = bounded::<LogLine>(1);
+            let router = LogRouter::new(config, rx);
+            let runner = &router.default;
+
+            let first = runner.new_file()?;
+            let name = first.file_name().unwrap_or_default().to_string_lossy();
+            assert!(name.starts_with(&format!("log_{}_", std::process::id())));
+            let second = runner.new_file()?;
+            assert_ne!(first, second);
+
+            // 同时创建时文件名也不重复
+            let files: Vec<PathBuf> = std::thread::scope(|s| {
+                let handles: Vec<_> = (0..8).map(|_| s.spawn(|| runner.new_file())).collect();
+                handles.into_iter().filter_map(|h| h.join().ok()).collect::<Result<_>>()
+            })?;
+            let unique: std::collections::HashSet<_> = files.iter().collect();
+            assert_eq!(unique.len(), 8);
+            assert!(!files.contains(&first) && !files.contains(&second));
+
+            let _ = std::fs::remove_dir_all(dir);
+            Ok(())
+        }
+
+        #[test]
+        fn test_failure() -> Result<()> {
+            let dir = crate::curr_dir!("test_failure")?;
+            let fallback = dir.join("fallback");
+            // 目录位置被文件占用，写入会失败
+            let blocked = dir.join("blocked");
+            blocked.clone().write_append(b"")?;
+            let policy = FailurePolicy::default()
+                .with_backoff(std::time::Duration::ZERO)
+                .with_fallback_dir(fallback.clone())
+                .with_stderr(false)
+                .with_buffer(1);
+            let runner = LogRunner::new(blocked.clone(), "log", 1024, None, Arc::new(policy));
+
+            assert!(runner.write("a".to_string()).is_err());
+            assert!(runner.write("b".to_string()).is_err());
+            let content = std::fs::read_to_string(fallback.join("log_fallback.txt"))?;
+            assert_eq!(content.lines().count(), 2);
+
+            std::fs::remove_file(&blocked)?;
+            runner.write("c".to_string())?;
+            let file = runner.curr_file.read().newerr()?.clone().newerr()?;
+            let content = std::fs::read_to_string(file)?;
+            let lines: Vec<&str> = content.lines().collect();
+            assert!(lines[0].contains("failed 2 times"));
+            assert!(lines[0].contains("1 lines held, 1 lines dropped"));
+            assert!(lines[1].ends_with("  b"));
+            assert!(lines[2].ends_with("  c"));
+
+            // 退出时不等待退避时间
+            let policy = FailurePolicy::default()
+                .with_backoff(std::time::Duration::from_secs(60))
+                .with_stderr(false);
+            let blocked = dir.join("blocked_flush");
+            blocked.clone().write_append(b"")?;
+            let runner = LogRunner::new(blocked.clone(), "log", 1024, None, Arc::new(policy));
+            assert!(runner.write("d".to_string()).is_err());
+            runner.write("e".to_string())?;
+            std::fs::remove_file(&blocked)?;
+            runner.flush_pending()?;
+            assert!(runner.failure.lock().newerr()?.is_none());
+            let file = runner.curr_file.read().newerr()?.clone().newerr()?;
+            let content = std::fs::read_to_string(&file)?;
+            let lines: Vec<&str> = content.lines().collect();
+            assert_eq!(lines.len(), 3);
+            assert!(lines[0].contains("2 lines held"));
+            assert!(lines[1].ends_with("  d"));
+            assert!(lines[2].ends_with("  e"));
+
+            // 已经写入恢复说明时不再重复写入
+            let mut f = WriteFailure::new();
+            f.notified = true;
+            f.pending.push_back("f\n".to_string());
+            runner.recover(&mut f)?;
+            let content = std::fs::read_to_string(&file)?;
+            assert_eq!(content.lines().count(), 4);
+            assert_eq!(content.lines().last(), Some("f"));
+
+            let _ = std::fs::remove_dir_all(dir);
+            Ok(())
         }
     }
 
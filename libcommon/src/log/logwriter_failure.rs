@@ -0,0 +1,143 @@
+use crate::if_feature;
+
+if_feature!("logfile" =>
+    use std::{
+        collections::VecDeque,
+        path::PathBuf,
+        time::{Duration, Instant},
+    };
+
+    use macro_builder::With;
+
+    use crate::ext::WriteAppendExt;
+
+    ///
+    /// 写入日志文件失败(如磁盘已满、没有权限)时的处理策略
+    ///
+    /// 失败后日志会保留在内存中并按退避时间重试，等待期间日志会写入备用目录或者stderr；
+    /// 恢复后会先写入一条恢复说明和保留的日志；调用[crate::log::log_flush]退出时不等待退避时间，再重试一次
+    ///
+    /// # example
+    ///
+    /// ```ignore
+    /// let policy = FailurePolicy::default()
+    ///     .with_fallback_dir("/tmp/log".into())
+    ///     .with_buffer(10_000);
+    /// let config = LogWriterConfig::new("/var/log/app").with_failure(policy);
+    /// ```
+    #[derive(With)]
+    pub struct FailurePolicy {
+        /**
+         * 首次重试前的等待时间，之后每次失败翻倍
+         */
+        backoff: Duration,
+        /**
+         * 最长的等待时间
+         */
+        max_backoff: Duration,
+        /**
+         * 失败时写入的备用目录
+         */
+        fallback_dir: Option<PathBuf>,
+        /**
+         * 没有备用目录或者备用目录也写入失败时，是否输出到stderr
+         */
+        stderr: bool,
+        /**
+         * 等待重试期间最多保留的日志数量，超出时丢弃最早的日志
+         */
+        buffer: usize,
+    }
+
+    impl Default for FailurePolicy {
+        fn default() -> Self {
+            Self {
+                backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(60),
+                fallback_dir: None,
+                stderr: true,
+                buffer: 1000,
+            }
+        }
+    }
+
+    /**
+     * 写入失败的状态，恢复后清除
+     */
+    pub(crate) struct WriteFailure {
+        since: chrono::DateTime<chrono::Local>,
+        failures: u32,
+        next_retry: Instant,
+        /**
+         * 等待写入的日志，已包含时间
+         */
+        pub(crate) pending: VecDeque<String>,
+        dropped: usize,
+        /**
+         * 是否已经写入恢复说明
+         */
+        pub(crate) notified: bool,
+    }
+
+    impl WriteFailure {
+        pub(crate) fn new() -> Self {
+            Self {
+                since: chrono::Local::now(),
+                failures: 0,
+                next_retry: Instant::now(),
+                pending: VecDeque::new(),
+                dropped: 0,
+                notified: false,
+            }
+        }
+
+        pub(crate) fn can_retry(&self) -> bool {
+            Instant::now() >= self.next_retry
+        }
+
+        /**
+         * 记录一次失败并计算下次重试的时间
+         */
+        pub(crate) fn failed(&mut self, policy: &FailurePolicy) {
+            self.failures += 1;
+            let backoff = policy
+                .backoff
+                .saturating_mul(2u32.saturating_pow(self.failures - 1))
+                .min(policy.max_backoff);
+            self.next_retry = Instant::now() + backoff;
+        }
+
+        /**
+         * 保留日志等待重试，同时写入备用目录或者stderr
+         */
+        pub(crate) fn hold(&mut self, policy: &FailurePolicy, name: &str, line: String) {
+            let fallback = policy
+                .fallback_dir
+                .as_ref()
+                .map(|dir| dir.join(format!("{name}_fallback.txt")).write_append(line.as_bytes()));
+            if !matches!(fallback, Some(Ok(_))) && policy.stderr {
+                eprint!("{line}");
+            }
+            if policy.buffer == 0 {
+                self.dropped += 1;
+                return;
+            }
+            if self.pending.len() >= policy.buffer {
+                self.pending.pop_front();
+                self.dropped += 1;
+            }
+            self.pending.push_back(line);
+        }
+
+        pub(crate) fn recovery_notice(&self) -> String {
+            let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let since = self.since.format("%Y-%m-%d %H:%M:%S");
+            format!(
+                "{now}  log write recovered: failed {} times since {since}, {} lines held, {} lines dropped\n",
+                self.failures,
+                self.pending.len(),
+                self.dropped
+            )
+        }
+    }
+);
@@ -0,0 +1,206 @@
+use crate::if_feature;
+
+if_feature!("logfile" =>
+    use std::path::Path;
+
+    use macro_builder::With;
+    use serde_json::{Map, Value};
+
+    use crate::log::{logger, redact};
+
+    /// 日志文件头部的字段
+    pub enum HeaderField {
+        /// 进程名
+        Process,
+        /// 进程id
+        Pid,
+        /// 主机名
+        Host,
+        /// 程序版本，见[LogHeader::with_version]
+        Version,
+        /// 命令行参数，会按照[crate::log::log_set_redact]设置的规则脱敏
+        Args,
+        /// 当前的日志级别
+        Level,
+        /// 上一个日志文件名
+        Previous,
+        /// 自定义字段，值会按照[crate::log::log_set_redact]设置的规则脱敏
+        Custom(String, String),
+    }
+
+    ///
+    /// 在每个日志文件开始时写入的头部信息，使每个文件都能说明是由哪个进程和版本写入的
+    ///
+    /// # example
+    ///
+    /// ```ignore
+    /// let header = LogHeader::new()
+    ///     .with_version(env!("CARGO_PKG_VERSION").to_string())
+    ///     .with_json(true);
+    /// let config = LogWriterConfig::new("./log").with_header(header);
+    /// ```
+    #[derive(With)]
+    pub struct LogHeader {
+        /**
+         * 需要写入的字段，按顺序输出
+         */
+        #[with(skip)]
+        fields: Vec<HeaderField>,
+        /**
+         * 是否以单行JSON写入
+         */
+        json: bool,
+        /**
+         * 程序版本，如`env!("CARGO_PKG_VERSION")`，未设置时输出`-`（JSON中为`null`）
+         */
+        version: Option<String>,
+    }
+
+    impl Default for LogHeader {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl LogHeader {
+        /// 包含除[HeaderField::Custom]以外的所有字段
+        pub fn new() -> Self {
+            Self::only(vec![
+                HeaderField::Process,
+                HeaderField::Pid,
+                HeaderField::Host,
+                HeaderField::Version,
+                HeaderField::Args,
+                HeaderField::Level,
+                HeaderField::Previous,
+            ])
+        }
+
+        /// 只包含指定的字段
+        pub fn only(fields: Vec<HeaderField>) -> Self {
+            Self {
+                fields,
+                json: false,
+                version: None,
+            }
+        }
+
+        /// 添加自定义字段
+        pub fn custom(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+            self.fields.push(HeaderField::Custom(key.into(), value.into()));
+            self
+        }
+
+        /// 生成头部内容，`previous`为上一个日志文件
+        pub(crate) fn render(&self, previous: Option<&Path>) -> String {
+            let values: Vec<(String, Value)> = self
+                .fields
+                .iter()
+                .map(|f| self.field(f, previous))
+                .collect();
+            if self.json {
+                let map: Map<String, Value> = values.into_iter().collect();
+                return format!("{}\n", Value::Object(map));
+            }
+            let mut s = String::from("==== log header ====\n");
+            for (k, v) in values {
+                let v = match v {
+                    Value::String(s) => s,
+                    Value::Array(a) => a
+                        .iter()
+                        .map(|v| v.as_str().map(str::to_string).unwrap_or(v.to_string()))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    Value::Null => "-".to_string(),
+                    v => v.to_string(),
+                };
+                s.push_str(&format!("{k}: {v}\n"));
+            }
+            s.push_str("====================\n");
+            s
+        }
+
+        fn field(&self, field: &HeaderField, previous: Option<&Path>) -> (String, Value) {
+            let (k, v) = match field {
+                HeaderField::Process => ("process", Value::from(process_name())),
+                HeaderField::Pid => ("pid", Value::from(std::process::id())),
+                HeaderField::Host => ("host", Value::from(host_name())),
+                HeaderField::Version => (
+                    "version",
+                    self.version.as_deref().map(Value::from).unwrap_or(Value::Null),
+                ),
+                HeaderField::Args => ("args", Value::from(redact::redact_args(std::env::args()))),
+                HeaderField::Level => ("level", Value::from(logger::log_level().to_string())),
+                HeaderField::Previous => (
+                    "previous",
+                    previous
+                        .and_then(|p| p.file_name())
+                        .map(|n| Value::from(n.to_string_lossy()))
+                        .unwrap_or(Value::Null),
+                ),
+                HeaderField::Custom(k, v) => {
+                    return (k.clone(), Value::from(redact::redact(v.clone())));
+                }
+            };
+            (k.to_string(), v)
+        }
+    }
+
+    fn process_name() -> String {
+        std::env::current_exe()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_default()
+    }
+
+    fn host_name() -> String {
+        ["HOSTNAME", "COMPUTERNAME"]
+            .iter()
+            .find_map(|k| std::env::var(k).ok())
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_header() {
+            let header = LogHeader::only(vec![HeaderField::Pid, HeaderField::Previous])
+                .custom("app", "test")
+                .with_version("1.0.0".to_string());
+            let s = header.render(Some(Path::new("log/log_202601010000.txt")));
+            assert!(s.contains(&format!("pid: {}\n", std::process::id())));
+            assert!(s.contains("previous: log_202601010000.txt\n"));
+            assert!(s.contains("app: test\n"));
+
+            let header = header.with_json(true);
+            let s = header.render(None);
+            let v: Value = serde_json::from_str(&s).unwrap();
+            assert_eq!(v["pid"], std::process::id());
+            assert!(v["previous"].is_null());
+            assert_eq!(v["app"], "test");
+
+            let header = LogHeader::only(vec![HeaderField::Version]);
+            assert_eq!(header.render(None), "==== log header ====\nversion: -\n====================\n");
+            let s = header.with_version("1.0.0".to_string()).render(None);
+            assert!(s.contains("version: 1.0.0\n"));
+        }
+
+        #[test]
+        fn test_header_redact() {
+            redact::test_setup_redact();
+            let header = LogHeader::only(vec![HeaderField::Args])
+                .custom("db", "password=hunter2")
+                .with_json(true);
+            let v: Value = serde_json::from_str(&header.render(None)).unwrap();
+            assert_eq!(v["db"], "password=******");
+            let args: Vec<String> = std::env::args().collect();
+            assert_eq!(v["args"], Value::from(redact::redact_args(args)));
+            let args = redact::redact_args(["app", "--password", "p w", "--password=x"].map(String::from));
+            assert_eq!(args, ["app", "--password", "******", "--password=******"]);
+        }
+    }
+);
@@ -3,11 +3,32 @@
 pub(crate) mod logger;
 pub(crate) mod logwriter;
 pub(crate) mod logwriter_default;
+pub(crate) mod logwriter_failure;
+pub(crate) mod logwriter_header;
+pub(crate) mod logtracing;
+pub(crate) mod recorder;
+pub(crate) mod redact;
+pub(crate) mod sample;
+pub(crate) mod stats;
 
 pub use logger::{log_setup, log_setup_result,log_set_level};
+pub use recorder::{RecorderDump, log_recorder_dump, log_setup_recorder};
+pub use redact::{RedactRule, log_redact_count, log_set_redact};
+pub use sample::log_set_sampling;
+pub use stats::{LogStats, log_stats};
 
 #[cfg(feature = "logfile")]
-pub use logwriter::{LogWriterTask, log_flush, log_setup_with_writer};
+pub use logwriter::{
+    LogWriterConfig, LogWriterTask, log_flush, log_setup_with_config, log_setup_with_writer,
+};
+
+#[cfg(feature = "logfile")]
+pub use logwriter_failure::FailurePolicy;
+#[cfg(feature = "logfile")]
+pub use logwriter_header::{HeaderField, LogHeader};
+
+#[cfg(feature = "tracing")]
+pub use logtracing::{LogLayer, log_setup_tracing};
 
 #[cfg(feature = "logfile_default")]
 pub use logwriter_default::LogWriterDefaultTask;
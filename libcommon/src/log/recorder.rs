@@ -0,0 +1,203 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::{
+    ext::WriteAppendExt,
+    log::{logger, logwriter, redact},
+    newerr,
+    prelude::{ErrMapperExt, Result},
+};
+
+/// 飞行记录的输出位置
+pub enum RecorderDump {
+    /// 写入日志文件，需要开启`logfile`并通过`log_setup_with_writer`初始化
+    Writer,
+    /// 追加写入单独的文件
+    File(PathBuf),
+}
+
+/**
+ * 飞行记录
+ *
+ * 在内存中保存最近的日志，包括低于当前输出级别的日志；
+ * 在输出`error`日志、发生panic或者手动调用时输出
+ *
+ * 保存的是未脱敏的内容，输出时再按照[crate::log::log_set_redact]设置的规则脱敏
+ */
+struct Recorder {
+    capacity: usize,
+    buf: Mutex<VecDeque<String>>,
+    dump: RecorderDump,
+}
+
+impl Recorder {
+    fn record(&self, line: String) {
+        let time = chrono::Local::now().format("%H:%M:%S%.3f");
+        if let Ok(mut buf) = self.buf.lock() {
+            if buf.len() >= self.capacity {
+                buf.pop_front();
+            }
+            buf.push_back(format!("[{time}] {line}"));
+        }
+    }
+
+    fn dump(&self, reason: &str) -> Result<()> {
+        // 输出后清空，避免连续的错误重复输出相同的记录
+        let lines: Vec<String> = self.buf.lock().newerr()?.drain(..).collect();
+        let lines: Vec<String> = lines.into_iter().map(redact::redact).collect();
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let head = format!(
+            "==== flight recorder: {reason}, last {} records ====",
+            lines.len()
+        );
+        let tail = "==== flight recorder end ====".to_string();
+        let all = std::iter::once(head)
+            .chain(lines)
+            .chain(std::iter::once(tail));
+        match &self.dump {
+            RecorderDump::Writer => {
+                for line in all {
+                    logwriter::write(line)?;
+                }
+            }
+            RecorderDump::File(path) => {
+                let content: String = all.map(|l| l + "\n").collect();
+                path.clone().write_append(content.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+/// 开启飞行记录
+///
+/// 在内存中保存最近`capacity`条所有级别的日志，在输出`error`日志、发生panic或者调用[log_recorder_dump]时输出到`dump`
+///
+/// 只能设置一次，重复设置会返回错误
+///
+/// # example
+///
+/// ```ignore
+/// log_setup();
+/// log_set_level("info");
+/// log_setup_recorder(200, RecorderDump::File("crash.txt".into()))?;
+/// debug!("not shown, but recorded");
+/// error!("failed"); // 将之前的debug日志写入crash.txt
+/// ```
+pub fn log_setup_recorder(capacity: usize, dump: RecorderDump) -> Result<()> {
+    if capacity == 0 {
+        return Err(newerr!("flight recorder capacity must be greater than 0"));
+    }
+    let recorder = Recorder {
+        capacity,
+        buf: Mutex::new(VecDeque::with_capacity(capacity)),
+        dump,
+    };
+    RECORDER
+        .set(recorder)
+        .map_err(|_| newerr!("flight recorder already set"))?;
+    logger::update_max_level();
+
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        prev(info);
+        if let Some(recorder) = RECORDER.get() {
+            // 与其它记录一样，在输出时脱敏
+            recorder.record(format!("PANIC: {info}"));
+            if let Err(e) = recorder.dump("panic") {
+                eprintln!("flight recorder dump failed {e:?}");
+            }
+        }
+    }));
+    Ok(())
+}
+
+/// 手动输出飞行记录
+pub fn log_recorder_dump() -> Result<()> {
+    match RECORDER.get() {
+        Some(recorder) => recorder.dump("requested"),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn is_enabled() -> bool {
+    RECORDER.get().is_some()
+}
+
+pub(crate) fn record(line: String) {
+    if let Some(recorder) = RECORDER.get() {
+        recorder.record(line);
+    }
+}
+
+pub(crate) fn dump_on_error() {
+    if let Some(recorder) = RECORDER.get()
+        && let Err(e) = recorder.dump("error")
+    {
+        eprintln!("flight recorder dump failed {e:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{curr_dir, log::log_setup};
+
+    #[test]
+    fn test_recorder() -> Result<()> {
+        let path = curr_dir!("test_recorder.txt")?;
+        let recorder = Recorder {
+            capacity: 2,
+            buf: Mutex::new(VecDeque::new()),
+            dump: RecorderDump::File(path.clone()),
+        };
+        recorder.record("DEBUG: a".to_string());
+        recorder.record("DEBUG: b".to_string());
+        recorder.record("ERROR: c".to_string());
+        recorder.dump("error")?;
+        // 已清空，不会重复输出
+        recorder.dump("error")?;
+
+        let content = fs::read_to_string(&path)?;
+        let _ = fs::remove_file(&path);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("error, last 2 records"));
+        assert!(lines[1].ends_with("DEBUG: b"));
+        assert!(lines[2].ends_with("ERROR: c"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recorder_dump() -> Result<()> {
+        log_setup();
+        redact::test_setup_redact();
+        // 开启后其它测试的error日志和panic也会写入该文件，因此放在临时目录中
+        let path =
+            std::env::temp_dir().join(format!("libcommon_recorder_{}.txt", std::process::id()));
+        log_setup_recorder(100, RecorderDump::File(path.clone()))?;
+
+        // 其它测试的日志也可能写入同一个文件，只检查本测试的内容
+        log::trace!("recorder trace password=hunter2");
+        log::error!("recorder error");
+        let content = fs::read_to_string(&path)?;
+        assert!(content.contains("TRACT: recorder trace password=******"));
+        assert!(content.contains("ERROR: recorder error"));
+
+        let _ = std::panic::catch_unwind(|| panic!("recorder panic password=hunter2"));
+        let content = fs::read_to_string(&path)?;
+        assert!(content.contains("flight recorder: panic"));
+        assert!(content.contains("recorder panic password=******"));
+        assert!(!content.contains("hunter2"));
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use regex::{Captures, Regex};
+
+use crate::newerr;
+use crate::prelude::Result;
+
+/// 脱敏后的替换内容
+const MASK: &str = "******";
+
+/// 日志脱敏规则
+///
+/// 日志在输出到控制台和写入文件之前，会将匹配的内容替换为`******`
+pub enum RedactRule {
+    /// 自定义正则，匹配到的内容全部替换
+    Regex(String),
+    /// 键名，忽略大小写，只替换值
+    ///
+    /// 如`Key("password")`会替换`password=xxx`、`password: "xxx"`、`"db_password":"xxx"`中的`xxx`
+    ///
+    /// 引号中的值整体替换（包括空格和转义的引号），`Some(..)`只替换括号内的值；
+    /// 未加引号的值替换到下一个分隔符（空白、`,`、`;`、`&`、`}`、`]`、`)`）为止
+    Key(String),
+    /// `Bearer xxx`形式的认证信息，只替换`xxx`
+    Bearer,
+    /// 信用卡号，允许以空格或`-`分隔
+    ///
+    /// 只替换已知发卡机构前缀的15位（American Express）或16位（Visa、Mastercard、Discover）数字，
+    /// 并且需要通过Luhn校验，避免替换时间戳、id等普通数字
+    CreditCard,
+}
+
+impl RedactRule {
+    /// 常用规则：`password`、`passwd`、`secret`、`token`、`api_key`，`Bearer`以及信用卡号
+    pub fn defaults() -> Vec<RedactRule> {
+        let mut rules: Vec<RedactRule> = ["password", "passwd", "secret", "token", "api_key"]
+            .into_iter()
+            .map(|k| RedactRule::Key(k.to_string()))
+            .collect();
+        rules.push(RedactRule::Bearer);
+        rules.push(RedactRule::CreditCard);
+        rules
+    }
+
+    fn compile(self) -> Result<Compiled> {
+        let compiled = match self {
+            RedactRule::Regex(re) => Compiled::All(Regex::new(&re)?),
+            RedactRule::Key(key) => Compiled::Key(Regex::new(&format!(
+                r#"(?i)(\b[\w-]*{}["']?\s*[:=]\s*)({}|{}|{}|{})"#,
+                regex::escape(&key),
+                // Some("xxx")、Some(xxx)
+                r#"Some\((?:"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|[^)]*)\)"#,
+                // "xxx"、'xxx'，可以包含空格和转义的引号
+                r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'"#,
+                // 未闭合的引号，替换到行尾
+                r#"["'][^\n]*"#,
+                r#"[^\s"',;&}\])]+"#,
+            ))?),
+            RedactRule::Bearer => Compiled::Value(Regex::new(r"(?i)(\bbearer\s+)[\w\-.~+/]+=*")?),
+            RedactRule::CreditCard => Compiled::Card(Regex::new(r"\b\d(?:[ -]?\d){14,15}\b")?),
+        };
+        Ok(compiled)
+    }
+}
+
+enum Compiled {
+    /// 替换全部匹配内容
+    All(Regex),
+    /// 保留第一个分组，替换其后的内容
+    Value(Regex),
+    /// 保留第一个分组（键名），替换第二个分组（值），见[mask_value]
+    Key(Regex),
+    /// 是信用卡号时替换全部匹配内容，见[is_card]
+    Card(Regex),
+}
+
+pub(crate) struct Redactor {
+    rules: Vec<Compiled>,
+    count: AtomicUsize,
+}
+
+impl Redactor {
+    pub(crate) fn new(rules: Vec<RedactRule>) -> Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(RedactRule::compile)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            count: AtomicUsize::new(0),
+        })
+    }
+
+    pub(crate) fn apply(&self, s: String) -> String {
+        self.replace(s, true)
+    }
+
+    /// 是否有规则匹配，不计入脱敏次数
+    fn matches(&self, s: &str) -> bool {
+        self.replace(s.to_string(), false) != s
+    }
+
+    ///
+    /// 脱敏命令行参数，`--key=value`和`--key value`两种形式的值都会被替换
+    pub(crate) fn apply_args(&self, args: impl IntoIterator<Item = String>) -> Vec<String> {
+        let mut mask_next = false;
+        args.into_iter()
+            .map(|arg| {
+                if std::mem::take(&mut mask_next) && !arg.starts_with('-') {
+                    self.count.fetch_add(1, Ordering::Relaxed);
+                    return MASK.to_string();
+                }
+                // `--key value`形式，按`--key=value`判断是否需要替换下一个参数
+                if arg.starts_with('-') && !arg.contains('=') {
+                    mask_next = self.matches(&format!("{arg}=value"));
+                }
+                self.apply(arg)
+            })
+            .collect()
+    }
+
+    fn replace(&self, mut s: String, counted: bool) -> String {
+        let count = || {
+            if counted {
+                self.count.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        for rule in &self.rules {
+            let replaced = match rule {
+                Compiled::All(re) => re.replace_all(&s, |_: &Captures| {
+                    count();
+                    MASK.to_string()
+                }),
+                Compiled::Value(re) => re.replace_all(&s, |c: &Captures| {
+                    count();
+                    format!("{}{MASK}", &c[1])
+                }),
+                Compiled::Key(re) => re.replace_all(&s, |c: &Captures| {
+                    count();
+                    format!("{}{}", &c[1], mask_value(&c[2]))
+                }),
+                Compiled::Card(re) => re.replace_all(&s, |c: &Captures| {
+                    if is_card(&c[0]) {
+                        count();
+                        MASK.to_string()
+                    } else {
+                        c[0].to_string()
+                    }
+                }),
+            };
+            // 未发生替换时返回的是借用，无需重新分配
+            if let Cow::Owned(new) = replaced {
+                s = new;
+            }
+        }
+        s
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+/// 替换值，保留外层的引号和`Some(..)`，如`"a b"`替换为`"******"`
+fn mask_value(value: &str) -> String {
+    if let Some(inner) = value
+        .strip_prefix("Some(")
+        .and_then(|v| v.strip_suffix(')'))
+    {
+        return format!("Some({})", mask_value(inner));
+    }
+    match value.chars().next() {
+        Some(q @ ('"' | '\'')) if value.len() > 1 && value.ends_with(q) => format!("{q}{MASK}{q}"),
+        Some(q @ ('"' | '\'')) => format!("{q}{MASK}"),
+        _ => MASK.to_string(),
+    }
+}
+
+/// 15位或16位数字，以已知的发卡机构前缀开头，并且通过Luhn校验
+fn is_card(s: &str) -> bool {
+    let digits: String = s.chars().filter(char::is_ascii_digit).collect();
+    let prefix = |n: usize| digits[..n].parse::<u32>().unwrap_or(0);
+    let issuer = match digits.len() {
+        // American Express
+        15 => matches!(prefix(2), 34 | 37),
+        // Visa、Mastercard、Discover
+        16 => {
+            digits.starts_with('4')
+                || (51..=55).contains(&prefix(2))
+                || (2221..=2720).contains(&prefix(4))
+                || prefix(4) == 6011
+                || prefix(2) == 65
+        }
+        _ => false,
+    };
+    issuer && luhn(&digits)
+}
+
+fn luhn(s: &str) -> bool {
+    let digits: Vec<u32> = s.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// 设置日志脱敏规则
+///
+/// 只能设置一次，应在初始化日志时设置；重复设置或者正则有误会返回错误
+///
+/// # example
+///
+/// ```ignore
+/// log_setup();
+/// log_set_redact(RedactRule::defaults())?;
+/// debug!("password=123456"); // DEBUG: password=******
+/// ```
+pub fn log_set_redact(rules: Vec<RedactRule>) -> Result<()> {
+    let redactor = Redactor::new(rules)?;
+    REDACTOR
+        .set(redactor)
+        .map_err(|_| newerr!("log redact rules already set"))
+}
+
+/// 已脱敏的次数
+pub fn log_redact_count() -> usize {
+    REDACTOR.get().map(Redactor::count).unwrap_or(0)
+}
+
+pub(crate) fn redact(s: String) -> String {
+    match REDACTOR.get() {
+        Some(redactor) => redactor.apply(s),
+        None => s,
+    }
+}
+
+/// 脱敏命令行参数，见[Redactor::apply_args]
+#[allow(unused)]
+pub(crate) fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    match REDACTOR.get() {
+        Some(redactor) => redactor.apply_args(args),
+        None => args.into_iter().collect(),
+    }
+}
+
+/// 测试中使用的全局脱敏规则，只替换`password`
+#[cfg(test)]
+#[allow(unused)]
+pub(crate) fn test_setup_redact() {
+    let _ = log_set_redact(vec![RedactRule::Key("password".to_string())]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() -> Result<()> {
+        let redactor = Redactor::new(RedactRule::defaults())?;
+        let s = redactor.apply(r#"Req { user: "a", password: "123456", db_token: "abc" }"#.into());
+        assert_eq!(
+            s,
+            r#"Req { user: "a", password: "******", db_token: "******" }"#
+        );
+        let s = redactor.apply("Authorization: Bearer eyJhbGciOi.J9=".into());
+        assert_eq!(s, "Authorization: Bearer ******");
+        let s = redactor.apply("card 4111 1111 1111 1111, order 1234567890123".into());
+        assert_eq!(s, "card ******, order 1234567890123");
+        assert_eq!(redactor.count(), 4);
+        let s = redactor.apply("amex 3782-822463-10005 mc 5555555555554444".into());
+        assert_eq!(s, "amex ****** mc ******");
+        // 通过Luhn校验的时间戳和id不是信用卡号
+        assert!(luhn("1760000000008") && luhn("1760000000000008"));
+        let s = "created_at=1760000000008 id=1760000000000008";
+        assert_eq!(redactor.apply(s.into()), s);
+
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct Login {
+            password: Option<String>,
+            token: String,
+            secret: Option<String>,
+        }
+        let login = Login {
+            password: Some("hunter2".into()),
+            token: "abc def".into(),
+            secret: None,
+        };
+        let redactor = Redactor::new(RedactRule::defaults())?;
+        assert_eq!(
+            redactor.apply(format!("{login:?}")),
+            r#"Login { password: Some("******"), token: "******", secret: ****** }"#
+        );
+        assert_eq!(
+            redactor.apply(r#"password="p@ss w0rd" user=a"#.into()),
+            r#"password="******" user=a"#
+        );
+        assert_eq!(
+            redactor.apply(r#"{"api_key": "a \"b\" c", "n": 1}"#.into()),
+            r#"{"api_key": "******", "n": 1}"#
+        );
+        assert_eq!(
+            redactor.apply("token='x y' passwd=Some(p w) secret=\"cut".into()),
+            "token='******' passwd=Some(******) secret=\"******"
+        );
+        assert_eq!(redactor.count(), 8);
+
+        let redactor = Redactor::new(vec![RedactRule::Key("token".into())])?;
+        let args = [
+            "app",
+            "--token",
+            "abc",
+            "--token=def",
+            "-v",
+            "--token",
+            "--debug",
+        ];
+        assert_eq!(
+            redactor.apply_args(args.map(String::from)),
+            [
+                "app",
+                "--token",
+                "******",
+                "--token=******",
+                "-v",
+                "--token",
+                "--debug"
+            ]
+        );
+        assert_eq!(redactor.count(), 2);
+
+        let redactor = Redactor::new(vec![RedactRule::Regex(r"\d{3}-\d{4}".into())])?;
+        assert_eq!(redactor.apply("tel 555-1234".into()), "tel ******");
+        assert!(Redactor::new(vec![RedactRule::Regex("(".into())]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    cell::Cell,
+    str::FromStr,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::newerr;
+use crate::prelude::Result;
+
+/// 采样比例
+enum Rate {
+    /// 按概率保留，取值`0.0..=1.0`
+    Percent(f64),
+    /// 每N条保留1条
+    Every(u64),
+}
+
+struct SampleRule {
+    /// 模块前缀，为空时匹配所有日志
+    target: String,
+    /// 该级别及更详细的日志会被采样
+    level: log::Level,
+    rate: Rate,
+    count: AtomicU64,
+}
+
+impl SampleRule {
+    fn matches(&self, metadata: &log::Metadata) -> bool {
+        let target = metadata.target();
+        metadata.level() >= self.level
+            && (self.target.is_empty()
+                || target
+                    .strip_prefix(&self.target)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::")))
+    }
+
+    fn keep(&self) -> bool {
+        match self.rate {
+            Rate::Percent(p) => random() < p,
+            Rate::Every(n) => self.count.fetch_add(1, Ordering::Relaxed).is_multiple_of(n),
+        }
+    }
+}
+
+impl FromStr for SampleRule {
+    type Err = crate::prelude::Err;
+
+    /// `target=level@rate`或者`level@rate`
+    fn from_str(s: &str) -> Result<Self> {
+        let (filter, rate) = s
+            .split_once('@')
+            .ok_or_else(|| newerr!("sampling directive must contain rate: {s}"))?;
+        let (target, level) = match filter.split_once('=') {
+            Some((target, level)) => (target.trim(), level),
+            None => ("", filter),
+        };
+        let level = log::Level::from_str(level.trim())
+            .map_err(|_| newerr!("invalid level in sampling directive: {s}"))?;
+        let rate = rate.trim();
+        let rate = if let Some(p) = rate.strip_suffix('%') {
+            let p: f64 = p.trim().parse()?;
+            if !(0.0..=100.0).contains(&p) {
+                return Err(newerr!("sampling percent must be in 0..=100: {s}"));
+            }
+            Rate::Percent(p / 100.0)
+        } else if let Some(n) = rate.strip_prefix("1/") {
+            let n: u64 = n.trim().parse()?;
+            if n == 0 {
+                return Err(newerr!("sampling every must be greater than 0: {s}"));
+            }
+            Rate::Every(n)
+        } else {
+            return Err(newerr!("sampling rate must be `N%` or `1/N`: {s}"));
+        };
+        Ok(Self {
+            target: target.to_string(),
+            level,
+            rate,
+            count: AtomicU64::new(0),
+        })
+    }
+}
+
+pub(crate) struct Sampler {
+    rules: Vec<SampleRule>,
+}
+
+impl FromStr for Sampler {
+    type Err = crate::prelude::Err;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rules = s
+            .split(',')
+            .filter(|d| !d.trim().is_empty())
+            .map(SampleRule::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+}
+
+impl Sampler {
+    /// 使用第一条匹配的规则判断是否丢弃
+    pub(crate) fn sampled_out(&self, metadata: &log::Metadata) -> bool {
+        self.rules
+            .iter()
+            .find(|r| r.matches(metadata))
+            .is_some_and(|r| !r.keep())
+    }
+}
+
+/// 返回`0.0..1.0`的伪随机数，采样和重试不需要密码学安全
+pub(crate) fn random() -> f64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0x2545_f491_4f6c_dd1d)
+                | 1,
+        );
+    }
+    STATE.with(|state| {
+        // xorshift64*
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+static SAMPLER: OnceLock<Sampler> = OnceLock::new();
+
+/// 设置日志采样
+///
+/// 使用`target=level@rate`的形式，多条以`,`分隔，按顺序使用第一条匹配的规则
+/// - `target`: 模块前缀，可省略，省略时匹配所有日志
+/// - `level`: 该级别及更详细的日志会被采样
+/// - `rate`: `N%`按概率保留，`1/N`每N条保留1条
+///
+/// 只对达到输出级别的日志采样：低于输出级别的日志计入`dropped_filtered`，不计入`dropped_sampled`，也不影响`1/N`的计数
+///
+/// 被丢弃的日志不会被格式化；开启飞行记录（[crate::log::log_setup_recorder]）时，被丢弃的日志仍会进入飞行记录
+///
+/// 只能设置一次，重复设置或者格式有误会返回错误
+///
+/// # example
+///
+/// ```ignore
+/// log_setup();
+/// log_set_sampling("net::packet=trace@1%, app::hot=debug@1/100")?;
+/// ```
+pub fn log_set_sampling(directives: &str) -> Result<()> {
+    let sampler = Sampler::from_str(directives)?;
+    SAMPLER
+        .set(sampler)
+        .map_err(|_| newerr!("log sampling already set"))
+}
+
+pub(crate) fn sampled_out(metadata: &log::Metadata) -> bool {
+    SAMPLER.get().is_some_and(|s| s.sampled_out(metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(target: &str, level: log::Level) -> log::Metadata<'_> {
+        log::Metadata::builder().target(target).level(level).build()
+    }
+
+    #[test]
+    fn test_sample() -> Result<()> {
+        let sampler = Sampler::from_str("app::hot=debug@1/10, net=trace@0%")?;
+        let hot = metadata("app::hot::inner", log::Level::Debug);
+        let kept = (0..100).filter(|_| !sampler.sampled_out(&hot)).count();
+        assert_eq!(kept, 10);
+        assert!(!sampler.sampled_out(&metadata("app::hot", log::Level::Info)));
+        assert!(!sampler.sampled_out(&metadata("app::hotter", log::Level::Debug)));
+        assert!(sampler.sampled_out(&metadata("net", log::Level::Trace)));
+        assert!(!sampler.sampled_out(&metadata("net", log::Level::Debug)));
+
+        let sampler = Sampler::from_str("trace@100%")?;
+        assert!(!sampler.sampled_out(&metadata("any", log::Level::Trace)));
+
+        assert!(Sampler::from_str("app=debug").is_err());
+        assert!(Sampler::from_str("app=debug@1/0").is_err());
+        assert!(Sampler::from_str("app=verbose@1%").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::log::logwriter;
+
+/// 日志的统计信息快照，见[log_stats]
+#[derive(Debug, Clone, Default)]
+pub struct LogStats {
+    /// 各级别已输出的日志数量
+    pub error: u64,
+    pub warn: u64,
+    pub info: u64,
+    pub debug: u64,
+    pub trace: u64,
+    /// 到达日志但因低于输出级别而未输出的数量
+    ///
+    /// 低于[log::max_level]的日志不会到达日志，不会被统计
+    pub dropped_filtered: u64,
+    /// 被采样丢弃的数量，见[crate::log::log_set_sampling]
+    pub dropped_sampled: u64,
+    /// 无法发送到写入线程而丢弃的数量
+    pub dropped_unsent: u64,
+    /// 写入日志文件的字节数
+    pub bytes_written: u64,
+    /// 日志文件轮转的次数
+    pub rotations: u64,
+    /// 写入日志文件失败的次数
+    pub write_errors: u64,
+    /// 最近一次写入失败的原因
+    pub last_write_error: Option<String>,
+    /// 等待写入文件的日志数量
+    pub queue_depth: usize,
+}
+
+static RECORDS: [AtomicU64; 5] = [const { AtomicU64::new(0) }; 5];
+static DROPPED_FILTERED: AtomicU64 = AtomicU64::new(0);
+static DROPPED_SAMPLED: AtomicU64 = AtomicU64::new(0);
+static DROPPED_UNSENT: AtomicU64 = AtomicU64::new(0);
+static BYTES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+static ROTATIONS: AtomicU64 = AtomicU64::new(0);
+static WRITE_ERRORS: AtomicU64 = AtomicU64::new(0);
+static LAST_WRITE_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+///
+/// 获取日志的统计信息
+///
+/// 可用于监控日志是否仍在正常写入，如`write_errors`持续增加或`bytes_written`不再变化
+pub fn log_stats() -> LogStats {
+    let records = |level: log::Level| RECORDS[level as usize - 1].load(Ordering::Relaxed);
+    LogStats {
+        error: records(log::Level::Error),
+        warn: records(log::Level::Warn),
+        info: records(log::Level::Info),
+        debug: records(log::Level::Debug),
+        trace: records(log::Level::Trace),
+        dropped_filtered: DROPPED_FILTERED.load(Ordering::Relaxed),
+        dropped_sampled: DROPPED_SAMPLED.load(Ordering::Relaxed),
+        dropped_unsent: DROPPED_UNSENT.load(Ordering::Relaxed),
+        bytes_written: BYTES_WRITTEN.load(Ordering::Relaxed),
+        rotations: ROTATIONS.load(Ordering::Relaxed),
+        write_errors: WRITE_ERRORS.load(Ordering::Relaxed),
+        last_write_error: LAST_WRITE_ERROR.lock().ok().and_then(|e| e.clone()),
+        queue_depth: logwriter::queue_depth(),
+    }
+}
+
+pub(crate) fn inc_record(level: log::Level) {
+    RECORDS[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn inc_filtered() {
+    DROPPED_FILTERED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn inc_sampled() {
+    DROPPED_SAMPLED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn inc_unsent() {
+    DROPPED_UNSENT.fetch_add(1, Ordering::Relaxed);
+}
+
+#[allow(unused)]
+pub(crate) fn add_bytes(n: usize) {
+    BYTES_WRITTEN.fetch_add(n as u64, Ordering::Relaxed);
+}
+
+#[allow(unused)]
+pub(crate) fn inc_rotation() {
+    ROTATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[allow(unused)]
+pub(crate) fn write_error(e: &impl std::fmt::Display) {
+    WRITE_ERRORS.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut last) = LAST_WRITE_ERROR.lock() {
+        *last = Some(e.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let before = log_stats();
+        inc_record(log::Level::Warn);
+        inc_filtered();
+        write_error(&"disk full");
+        let after = log_stats();
+        assert!(after.warn > before.warn);
+        assert!(after.dropped_filtered > before.dropped_filtered);
+        assert!(after.write_errors > before.write_errors);
+        assert_eq!(after.last_write_error.as_deref(), Some("disk full"));
+    }
+}
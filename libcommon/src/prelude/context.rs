@@ -0,0 +1,244 @@
+use std::{
+    fmt::{Debug, Display},
+    panic::Location,
+};
+
+use crate::prelude::{Err, ErrInfo, ErrInfoExt, ErrorKind, Result};
+
+///
+/// 为任意错误附加信息
+///
+/// 支持错误类型只实现了[Display]的[std::result::Result]以及[Option]，附加的信息会保留在[Err]中，
+/// 经过`?`传递后仍可以通过[ErrInfoExt]获取
+///
+/// 原错误为[Err]和[std::io::Error]时保留原错误，其它类型只保留输出；
+/// 需要保留[std::error::Error]的类型和错误链时，使用[ErrSourceExt]
+///
+/// # example
+/// ```
+/// use libcommon::prelude::{ErrContextExt, ErrInfoExt, Result};
+///
+/// fn parse(s: &str) -> Result<u32> {
+///     s.parse::<u32>()
+///         .newerr_ctx(|| format!("parse `{s}` failed"))
+///         .with_kv("input", s)
+/// }
+///
+/// let err = parse("a").unwrap_err();
+/// assert_eq!(err.to_string(), "parse `a` failed");
+/// assert_eq!(err.fields(), vec![("input".to_string(), "a".to_string())]);
+/// ```
+pub trait ErrContextExt<T> {
+    /// 出错时使用`f`生成的信息创建错误，原错误作为原因
+    fn newerr_ctx<C: Display, F: FnOnce() -> C>(self, f: F) -> Result<T>;
+
+    /// 出错时使用`msg`创建错误，原错误作为原因
+    fn newerr_msg(self, msg: impl Display) -> Result<T>;
+
+    /// 出错时附加字段，不改变错误信息
+    fn with_kv(self, key: impl Into<String>, value: impl Display) -> Result<T>;
+}
+
+///
+/// 将错误转为[Err]
+///
+/// [Err]、[anyhow::Error]和[std::io::Error]会保留原类型，以便保留错误链和[ErrorKind]；其它类型只保留其输出
+fn into_err<E: Display + Debug + Send + Sync + 'static>(e: E) -> Err {
+    // Err::msg创建的错误可以downcast为原类型
+    let e = match Err::msg(e).downcast::<Err>() {
+        Ok(e) => return e,
+        Err(e) => e,
+    };
+    let e = match e.downcast::<anyhow::Error>() {
+        Ok(e) => return Err::from(e),
+        Err(e) => e,
+    };
+    match e.downcast::<std::io::Error>() {
+        Ok(e) => Err::from(e),
+        Err(e) => e,
+    }
+}
+
+impl<T, E> ErrContextExt<T> for std::result::Result<T, E>
+where
+    E: Display + Debug + Send + Sync + 'static,
+{
+    #[track_caller]
+    fn newerr_ctx<C: Display, F: FnOnce() -> C>(self, f: F) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            ErrInfo::wrap(into_err(e), f().to_string())
+                .at(location)
+                .into()
+        })
+    }
+
+    #[track_caller]
+    fn newerr_msg(self, msg: impl Display) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            ErrInfo::wrap(into_err(e), msg.to_string())
+                .at(location)
+                .into()
+        })
+    }
+
+    fn with_kv(self, key: impl Into<String>, value: impl Display) -> Result<T> {
+        self.map_err(|e| into_err(e).with_kv(key, value))
+    }
+}
+
+///
+/// 为[std::error::Error]附加信息，原错误保留原类型作为原因
+///
+/// 与[ErrContextExt]不同，原错误的错误链会保留，也可以通过[anyhow::Error::downcast_ref]获取原错误
+///
+/// # example
+/// ```
+/// use libcommon::prelude::{ErrSourceExt, Result};
+///
+/// fn parse(s: &str) -> Result<u32> {
+///     s.parse::<u32>().source_ctx(|| format!("parse `{s}` failed"))
+/// }
+///
+/// let err = parse("a").unwrap_err();
+/// assert_eq!(format!("{err:#}"), "parse `a` failed: invalid digit found in string");
+/// assert!(err.chain().any(|e| e.is::<std::num::ParseIntError>()));
+/// ```
+pub trait ErrSourceExt<T> {
+    /// 出错时使用`f`生成的信息创建错误，原错误作为原因
+    fn source_ctx<C: Display, F: FnOnce() -> C>(self, f: F) -> Result<T>;
+
+    /// 出错时使用`msg`创建错误，原错误作为原因
+    fn source_msg(self, msg: impl Display) -> Result<T>;
+}
+
+impl<T, E> ErrSourceExt<T> for std::result::Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    #[track_caller]
+    fn source_ctx<C: Display, F: FnOnce() -> C>(self, f: F) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            ErrInfo::wrap(Err::from(e), f().to_string())
+                .at(location)
+                .into()
+        })
+    }
+
+    #[track_caller]
+    fn source_msg(self, msg: impl Display) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            ErrInfo::wrap(Err::from(e), msg.to_string())
+                .at(location)
+                .into()
+        })
+    }
+}
+
+impl<T> ErrContextExt<T> for Option<T> {
+    #[track_caller]
+    fn newerr_ctx<C: Display, F: FnOnce() -> C>(self, f: F) -> Result<T> {
+        let location = Location::caller();
+        self.ok_or_else(|| {
+            ErrInfo::new(ErrorKind::Other, f().to_string())
+                .at(location)
+                .into()
+        })
+    }
+
+    #[track_caller]
+    fn newerr_msg(self, msg: impl Display) -> Result<T> {
+        let location = Location::caller();
+        self.ok_or_else(|| {
+            ErrInfo::new(ErrorKind::Other, msg.to_string())
+                .at(location)
+                .into()
+        })
+    }
+
+    #[track_caller]
+    fn with_kv(self, key: impl Into<String>, value: impl Display) -> Result<T> {
+        let location = Location::caller();
+        self.ok_or_else(|| {
+            ErrInfo::new(ErrorKind::Other, "err from None")
+                .at(location)
+                .with_kv(key, value)
+                .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newerr;
+
+    fn load(id: u32) -> Result<String> {
+        let s = std::fs::read_to_string("/not/exists/file")
+            .newerr_ctx(|| format!("load user {id}"))
+            .with_kv("id", id)?;
+        Ok(s)
+    }
+
+    fn handle() -> Result<String> {
+        let s = load(1).with_kv("op", "handle")?;
+        Ok(s)
+    }
+
+    #[test]
+    fn test_context() {
+        let err = handle().unwrap_err();
+        assert_eq!(err.to_string(), "load user 1");
+        assert!(err.is_kind(ErrorKind::NotFound));
+        assert_eq!(err.chain().count(), 2);
+        let fields = err.fields();
+        assert_eq!(fields.len(), 2);
+        assert!(fields.contains(&("op".to_string(), "handle".to_string())));
+        assert!(format!("{err:#?}").contains("id=1"));
+
+        let err = Err::<(), _>("not std error").newerr_msg("msg").unwrap_err();
+        assert_eq!(format!("{err:#}"), "msg: not std error");
+
+        let err = Err::<(), Err>(newerr!("inner"))
+            .with_kv("a", 1)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "inner");
+        assert_eq!(err.chain().count(), 1);
+
+        let err = None::<()>.newerr_ctx(|| "no value").unwrap_err();
+        assert_eq!(err.to_string(), "no value");
+    }
+
+    #[derive(Debug)]
+    struct Outer(std::io::Error);
+
+    impl Display for Outer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl std::error::Error for Outer {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        let outer = || Err::<(), _>(Outer(std::io::Error::other("disk")));
+
+        let err = outer().source_msg("save").unwrap_err();
+        assert_eq!(format!("{err:#}"), "save: outer: disk");
+        assert_eq!(err.chain().count(), 3);
+        assert!(err.chain().nth(1).is_some_and(|e| e.is::<Outer>()));
+        assert_eq!(err.location().map(|l| l.file()), Some(file!()));
+
+        // 只保留输出
+        let err = outer().newerr_msg("save").unwrap_err();
+        assert_eq!(err.chain().count(), 2);
+    }
+}
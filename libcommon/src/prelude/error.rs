@@ -0,0 +1,331 @@
+use std::{
+    fmt::{Debug, Display},
+    panic::Location,
+};
+
+use crate::prelude::Err;
+
+/// 错误类型，用于区分错误而无需匹配错误信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    NotFound,
+    InvalidInput,
+    PermissionDenied,
+    AlreadyExists,
+    Timeout,
+    Unavailable,
+    Unsupported,
+    Io,
+    Internal,
+    /// 线程或者任务panic
+    Panic,
+    Other,
+}
+
+impl ErrorKind {
+    /// 稳定的错误码，可用于对外接口或者映射为状态码
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "NOT_FOUND",
+            ErrorKind::InvalidInput => "INVALID_INPUT",
+            ErrorKind::PermissionDenied => "PERMISSION_DENIED",
+            ErrorKind::AlreadyExists => "ALREADY_EXISTS",
+            ErrorKind::Timeout => "TIMEOUT",
+            ErrorKind::Unavailable => "UNAVAILABLE",
+            ErrorKind::Unsupported => "UNSUPPORTED",
+            ErrorKind::Io => "IO",
+            ErrorKind::Internal => "INTERNAL",
+            ErrorKind::Panic => "PANIC",
+            ErrorKind::Other => "OTHER",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl From<std::io::ErrorKind> for ErrorKind {
+    fn from(kind: std::io::ErrorKind) -> Self {
+        use std::io::ErrorKind as Io;
+        match kind {
+            Io::NotFound => ErrorKind::NotFound,
+            Io::PermissionDenied => ErrorKind::PermissionDenied,
+            Io::AlreadyExists => ErrorKind::AlreadyExists,
+            Io::InvalidInput | Io::InvalidData => ErrorKind::InvalidInput,
+            Io::TimedOut => ErrorKind::Timeout,
+            Io::Unsupported => ErrorKind::Unsupported,
+            Io::ConnectionRefused | Io::ConnectionReset | Io::ConnectionAborted => {
+                ErrorKind::Unavailable
+            }
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+///
+/// 带有[ErrorKind]和创建位置的错误，通过[crate::newerr]创建
+///
+/// 转为[Err]后可以通过[ErrInfoExt]获取
+///
+/// `{}`只输出错误信息；`{:?}`还会输出错误类型、附加字段和创建位置，
+/// 转为[Err]后`{:?}`输出整个错误链的这些信息，见[crate::prelude::ErrReportExt::report]
+pub struct ErrInfo {
+    kind: ErrorKind,
+    msg: String,
+    location: Option<&'static Location<'static>>,
+    /// 附加的字段，见[crate::prelude::ErrContextExt::with_kv]
+    fields: Vec<(String, String)>,
+    source: Option<Err>,
+    /// 只用于附加字段，不作为错误链中单独的一层
+    transparent: bool,
+}
+
+impl ErrInfo {
+    /// 记录调用位置
+    #[track_caller]
+    pub fn new(kind: ErrorKind, msg: impl Into<String>) -> Self {
+        Self {
+            kind,
+            msg: msg.into(),
+            location: Some(Location::caller()),
+            fields: vec![],
+            source: None,
+            transparent: false,
+        }
+    }
+
+    /// 以`source`作为原因，错误类型与`source`相同
+    #[track_caller]
+    pub fn wrap(source: Err, msg: impl Into<String>) -> Self {
+        let mut info = Self::new(source.kind(), msg);
+        info.source = Some(source);
+        info
+    }
+
+    /// 包装`source`以附加字段，输出与`source`相同
+    pub(crate) fn transparent(source: Err) -> Self {
+        Self {
+            kind: source.kind(),
+            msg: source.to_string(),
+            location: source.location(),
+            fields: vec![],
+            source: Some(source),
+            transparent: true,
+        }
+    }
+
+    /// 附加字段
+    pub fn with_kv(mut self, key: impl Into<String>, value: impl Display) -> Self {
+        self.fields.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// 替换错误类型
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// 替换记录的位置，用于在闭包中创建错误时保留外部的调用位置
+    pub fn at(mut self, location: &'static Location<'static>) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+}
+
+impl Display for ErrInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl Debug for ErrInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)?;
+        if self.kind != ErrorKind::Other {
+            write!(f, " [{}]", self.kind)?;
+        }
+        if !self.fields.is_empty() {
+            let fields: Vec<String> = self
+                .fields
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            write!(f, " {{{}}}", fields.join(", "))?;
+        }
+        if let Some(location) = self.location {
+            write!(f, "\n    at {location}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ErrInfo {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        let source: &(dyn std::error::Error + 'static) = self.source.as_ref()?.as_ref();
+        if self.transparent {
+            source.source()
+        } else {
+            Some(source)
+        }
+    }
+}
+
+/// 获取[Err]中[ErrInfo]记录的信息
+pub trait ErrInfoExt {
+    ///
+    /// 错误类型
+    ///
+    /// 依次查找错误链中的[ErrInfo]和[std::io::Error]，都没有时为[ErrorKind::Other]
+    fn kind(&self) -> ErrorKind;
+
+    ///
+    /// 是否为该类型的错误
+    ///
+    /// 因为[anyhow::Error::is]的存在，无法使用`is`作为方法名
+    fn is_kind(&self, kind: ErrorKind) -> bool {
+        self.kind() == kind
+    }
+
+    ///
+    /// 错误的创建位置
+    ///
+    /// 由[crate::newerr]或者[crate::prelude::ErrMapperExt]创建时记录
+    fn location(&self) -> Option<&'static Location<'static>>;
+
+    ///
+    /// 错误链中所有[ErrInfo]附加的字段，外层在前
+    fn fields(&self) -> Vec<(String, String)>;
+
+    ///
+    /// 附加字段
+    ///
+    /// 如果最外层是[ErrInfo]则直接附加，否则包装一层不改变输出的[ErrInfo]
+    fn with_kv(self, key: impl Into<String>, value: impl Display) -> Err;
+}
+
+impl ErrInfoExt for Err {
+    fn kind(&self) -> ErrorKind {
+        if let Some(info) = self.downcast_ref::<ErrInfo>() {
+            return info.kind;
+        }
+        for cause in self.chain() {
+            if let Some(info) = cause.downcast_ref::<ErrInfo>() {
+                return info.kind;
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                return e.kind().into();
+            }
+        }
+        ErrorKind::Other
+    }
+
+    fn location(&self) -> Option<&'static Location<'static>> {
+        if let Some(info) = self.downcast_ref::<ErrInfo>() {
+            return info.location;
+        }
+        self.chain()
+            .find_map(|cause| cause.downcast_ref::<ErrInfo>())
+            .and_then(|info| info.location)
+    }
+
+    fn fields(&self) -> Vec<(String, String)> {
+        self.chain()
+            .filter_map(|cause| cause.downcast_ref::<ErrInfo>())
+            .flat_map(|info| info.fields.iter().cloned())
+            .collect()
+    }
+
+    fn with_kv(mut self, key: impl Into<String>, value: impl Display) -> Err {
+        if let Some(info) = self.downcast_mut::<ErrInfo>() {
+            info.fields.push((key.into(), value.to_string()));
+            return self;
+        }
+        ErrInfo::transparent(self).with_kv(key, value).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        newerr,
+        prelude::{ErrMapperExt, Result},
+    };
+
+    fn find_user(id: u32) -> Result<()> {
+        Err(newerr!(kind = NotFound, "user {id}"))
+    }
+
+    fn read_file() -> Result<String> {
+        Ok(std::fs::read_to_string("/not/exists/file")?)
+    }
+
+    #[test]
+    fn test_kind() {
+        let err = find_user(1).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.is_kind(ErrorKind::NotFound));
+        assert_eq!(err.kind().code(), "NOT_FOUND");
+        assert_eq!(err.to_string(), "user 1");
+
+        let err = read_file().unwrap_err();
+        assert!(err.is_kind(ErrorKind::NotFound));
+
+        let err = newerr!(kind = Timeout, "{}", 30).context("request failed");
+        assert!(err.is_kind(ErrorKind::Timeout));
+
+        assert!(newerr!("no kind").is_kind(ErrorKind::Other));
+        let a = 1;
+        assert!(newerr!(kind = Internal, a).is_kind(ErrorKind::Internal));
+    }
+
+    #[test]
+    fn test_location() {
+        let line = line!() + 1;
+        let err = newerr!("test location {}", 1);
+        let location = err.location().unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+        assert!(format!("{err:#?}").contains(&format!("at {}:{line}", file!())));
+        assert_eq!(err.to_string(), "test location 1");
+
+        let line = line!() + 1;
+        let err = newerr!(kind = NotFound, "user {}", 1)
+            .with_kv("id", 1)
+            .context("load failed");
+        let debug = format!("{err:?}");
+        assert!(debug.starts_with(&format!(
+            "load failed\nCaused by:\n    0: user 1 [NOT_FOUND] {{id=1}}\n           at {}:{line}:19",
+            file!()
+        )));
+        let captured = err.backtrace().status() == std::backtrace::BacktraceStatus::Captured;
+        assert_eq!(debug.contains("\nBacktrace:\n"), captured);
+        let err = read_file().unwrap_err().with_kv("path", "/not/exists/file");
+        assert!(format!("{err:?}").starts_with("No such file"));
+        assert!(format!("{err:?}").contains("[NOT_FOUND] {path=/not/exists/file}"));
+
+        let line = line!() + 1;
+        let err = None::<()>.newerr().unwrap_err();
+        assert_eq!(err.location().unwrap().line(), line);
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt::{Debug, Display};
+
+use crate::prelude::{Err, ErrInfoExt};
+
+///
+/// 多个错误的集合
+///
+/// 用于批量处理时收集所有失败，而不是在第一个错误时停止，见[ErrorsIterExt]
+///
+/// `{}`输出编号的错误列表，每项包含其错误链；`{:?}`还会输出每项的创建位置
+#[derive(Default)]
+pub struct Errors {
+    errors: Vec<Err>,
+}
+
+impl Errors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, err: impl Into<Err>) {
+        self.errors.push(err.into());
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Err> {
+        self.errors.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<Err> {
+        self.errors
+    }
+
+    /// 没有错误时返回`Ok(value)`
+    pub fn into_result<T>(self, value: T) -> std::result::Result<T, Errors> {
+        if self.is_empty() {
+            Ok(value)
+        } else {
+            Err(self)
+        }
+    }
+
+    fn header(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.errors.len() {
+            1 => f.write_str("1 error:"),
+            n => write!(f, "{n} errors:"),
+        }
+    }
+}
+
+impl From<Vec<Err>> for Errors {
+    fn from(errors: Vec<Err>) -> Self {
+        Self { errors }
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = Err;
+    type IntoIter = std::vec::IntoIter<Err>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a Err;
+    type IntoIter = std::slice::Iter<'a, Err>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.header(f)?;
+        for (i, e) in self.errors.iter().enumerate() {
+            write!(f, "\n  {}. {e:#}", i + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.header(f)?;
+        for (i, e) in self.errors.iter().enumerate() {
+            write!(f, "\n  {}. {e:#}", i + 1)?;
+            if let Some(location) = e.location() {
+                write!(f, "\n       at {location}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Errors {}
+
+///
+/// 执行所有可能失败的项，返回全部结果或者全部错误
+///
+/// # example
+/// ```
+/// use libcommon::prelude::{ErrorsIterExt, Result};
+///
+/// fn parse(s: &str) -> Result<u32> {
+///     Ok(s.parse()?)
+/// }
+///
+/// let values = ["1", "2"].into_iter().try_all(parse).unwrap();
+/// assert_eq!(values, vec![1, 2]);
+///
+/// let errors = ["a", "2", "b"].into_iter().try_all(parse).unwrap_err();
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub trait ErrorsIterExt: Iterator + Sized {
+    /// 收集所有的`Ok`值，有任意错误时返回所有错误
+    fn collect_all_errors<T, E>(self) -> std::result::Result<Vec<T>, Errors>
+    where
+        Self: Iterator<Item = std::result::Result<T, E>>,
+        E: Into<Err>,
+    {
+        let mut values = vec![];
+        let mut errors = Errors::new();
+        for item in self {
+            match item {
+                Ok(v) => values.push(v),
+                Err(e) => errors.push(e),
+            }
+        }
+        errors.into_result(values)
+    }
+
+    /// 对每一项执行`f`，不会在第一个错误时停止
+    fn try_all<T, E, F>(self, f: F) -> std::result::Result<Vec<T>, Errors>
+    where
+        F: FnMut(Self::Item) -> std::result::Result<T, E>,
+        E: Into<Err>,
+    {
+        self.map(f).collect_all_errors()
+    }
+}
+
+impl<I: Iterator> ErrorsIterExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        newerr,
+        prelude::{ErrContextExt, Result},
+    };
+
+    fn check(i: u32) -> Result<u32> {
+        if i.is_multiple_of(2) {
+            Ok(i)
+        } else {
+            Err(newerr!("odd {i}"))
+        }
+    }
+
+    fn batch() -> Result<Vec<u32>> {
+        Ok((0..4).try_all(check)?)
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!([0, 2].into_iter().try_all(check).unwrap(), vec![0, 2]);
+
+        let errors = (0..4).try_all(check).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors.to_string(), "2 errors:\n  1. odd 1\n  2. odd 3");
+        assert!(format!("{errors:?}").contains(&format!("at {}", file!())));
+
+        let err = batch().newerr_msg("batch failed").unwrap_err();
+        assert_eq!(err.to_string(), "batch failed");
+        assert!(err.chain().any(|e| e.is::<Errors>()));
+
+        let errors = vec![Ok(1), Err(std::io::Error::other("io"))]
+            .into_iter()
+            .collect_all_errors()
+            .unwrap_err();
+        assert_eq!(errors.to_string(), "1 error:\n  1. io");
+        assert!(Errors::new().into_result(()).is_ok());
+    }
+}
@@ -1,6 +1,16 @@
+mod context;
+mod error;
+mod errors;
+mod panic;
+mod report;
 mod result;
 
 pub use crate::log::log_setup;
 pub use crate::logsetup;
+pub use crate::prelude::context::{ErrContextExt, ErrSourceExt};
+pub use crate::prelude::error::{ErrInfo, ErrInfoExt, ErrorKind};
+pub use crate::prelude::errors::{Errors, ErrorsIterExt};
+pub use crate::prelude::panic::panic_message;
+pub use crate::prelude::report::ErrReportExt;
 pub use crate::prelude::result::{Err, ErrMapperExt, Result};
 pub use log::{debug, error, info, trace, warn};
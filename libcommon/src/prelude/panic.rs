@@ -0,0 +1,157 @@
+use std::{any::Any, panic::Location, thread::JoinHandle};
+
+use crate::prelude::{Err, ErrMapperExt, ErrorKind, Result, result::err_at};
+
+///
+/// 获取panic的信息
+///
+/// 支持`&str`、`String`、[std::error::Error]、[Err]和[anyhow::Error]，其它类型输出其类型说明
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else if let Some(e) = payload.downcast_ref::<Box<dyn std::error::Error + Send + Sync>>() {
+        e.to_string()
+    } else if let Some(e) = payload.downcast_ref::<Err>() {
+        e.to_string()
+    } else if let Some(e) = payload.downcast_ref::<anyhow::Error>() {
+        e.to_string()
+    } else {
+        "Box<dyn Any> panic payload".to_string()
+    }
+}
+
+fn panic_err(thread: Option<&str>, payload: &(dyn Any + Send)) -> String {
+    let msg = panic_message(payload);
+    match thread {
+        Some(name) => format!("thread '{name}' panicked: {msg}"),
+        None => format!("thread panicked: {msg}"),
+    }
+}
+
+impl<T> ErrMapperExt<T> for std::thread::Result<T> {
+    ///
+    /// 将[std::thread::Result]转换为[Result]
+    ///
+    /// panic会转为[ErrorKind::Panic]并包含panic的信息；
+    /// 无法获取线程名，需要线程名时使用[JoinHandle]的[ErrMapperExt::newerr]
+    ///
+    /// # example
+    /// ```
+    /// use std::thread;
+    /// use libcommon::prelude::{Result, ErrMapperExt};
+    ///
+    /// let result: Result<()> = thread::spawn(|| ()).join().newerr();
+    /// assert!(result.is_ok());
+    /// ```
+    #[track_caller]
+    fn newerr(self) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| err_at(ErrorKind::Panic, panic_err(None, e.as_ref()), location))
+    }
+}
+
+impl<T> ErrMapperExt<T> for JoinHandle<T> {
+    ///
+    /// 等待线程结束，并将结果转换为[Result]
+    ///
+    /// panic会转为[ErrorKind::Panic]并包含线程名和panic的信息
+    ///
+    /// # example
+    /// ```
+    /// use std::thread;
+    /// use libcommon::prelude::{ErrInfoExt, ErrMapperExt, ErrorKind};
+    ///
+    /// let handle = thread::Builder::new()
+    ///     .name("worker".to_string())
+    ///     .spawn(|| panic!("boom"))
+    ///     .unwrap();
+    /// let err = handle.newerr().unwrap_err();
+    /// assert!(err.is_kind(ErrorKind::Panic));
+    /// assert_eq!(err.to_string(), "thread 'worker' panicked: boom");
+    /// ```
+    #[track_caller]
+    fn newerr(self) -> Result<T> {
+        let location = Location::caller();
+        let name = self.thread().name().map(str::to_string);
+        self.join().map_err(|e| {
+            err_at(
+                ErrorKind::Panic,
+                panic_err(name.as_deref(), e.as_ref()),
+                location,
+            )
+        })
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T> ErrMapperExt<T> for std::result::Result<T, tokio::task::JoinError> {
+    ///
+    /// 将tokio任务的结果转换为[Result]
+    ///
+    /// panic会转为[ErrorKind::Panic]并包含任务id和panic的信息
+    #[track_caller]
+    fn newerr(self) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            let id = e.id();
+            if e.is_panic() {
+                let msg = panic_message(e.into_panic().as_ref());
+                err_at(
+                    ErrorKind::Panic,
+                    format!("task {id} panicked: {msg}"),
+                    location,
+                )
+            } else {
+                err_at(ErrorKind::Other, format!("task {id} cancelled"), location)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::prelude::ErrInfoExt;
+
+    #[test]
+    fn test_panic() {
+        let err = thread::spawn(|| panic!("boom {}", 1))
+            .join()
+            .newerr()
+            .unwrap_err();
+        assert!(err.is_kind(ErrorKind::Panic));
+        assert_eq!(err.to_string(), "thread panicked: boom 1");
+
+        let handle = thread::Builder::new()
+            .name("worker".to_string())
+            .spawn(|| std::panic::panic_any(crate::newerr!("err payload")))
+            .unwrap();
+        let err = handle.newerr().unwrap_err();
+        assert_eq!(err.to_string(), "thread 'worker' panicked: err payload");
+
+        let err = thread::spawn(|| std::panic::panic_any(1u8))
+            .join()
+            .newerr()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "thread panicked: Box<dyn Any> panic payload"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_panic() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let err = rt
+            .block_on(rt.spawn(async { panic!("task boom") }))
+            .newerr()
+            .unwrap_err();
+        assert!(err.is_kind(ErrorKind::Panic));
+        assert!(err.to_string().ends_with("panicked: task boom"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::{backtrace::BacktraceStatus, error::Error, fmt::Write, panic::Location};
+
+use colored::Colorize;
+use serde_json::{Map, Value, json};
+
+use crate::prelude::{Err, ErrInfo, ErrInfoExt, ErrorKind, Errors};
+
+///
+/// 输出错误报告
+///
+/// - [ErrReportExt::report]：多行文本，包括错误链、每层的类型、字段和创建位置，以及调用栈(设置了`RUST_BACKTRACE`时)
+/// - [ErrReportExt::report_colored]：同上，带颜色，用于终端输出
+/// - [ErrReportExt::report_json]：结构稳定的json，用于程序处理
+///
+/// # example
+/// ```
+/// use libcommon::{newerr, prelude::{ErrContextExt, ErrReportExt, Result}};
+///
+/// let err = Err::<(), _>(newerr!(kind = NotFound, "user 1"))
+///     .newerr_msg("load failed")
+///     .unwrap_err();
+/// assert!(err.report().starts_with("load failed [NOT_FOUND]"));
+/// assert_eq!(err.report_json()["chain"][1]["message"], "user 1");
+/// ```
+pub trait ErrReportExt {
+    fn report(&self) -> String;
+
+    fn report_colored(&self) -> String;
+
+    ///
+    /// 格式为:
+    /// ```json
+    /// {
+    ///     "message": "load failed",
+    ///     "kind": "NOT_FOUND",
+    ///     "location": "src/main.rs:10:5",
+    ///     "fields": {"id": "1"},
+    ///     "chain": [
+    ///         {"message": "load failed", "kind": "NOT_FOUND", "location": "src/main.rs:10:5", "fields": {"id": "1"}},
+    ///         {"message": "user 1", "kind": "NOT_FOUND", "location": "src/user.rs:3:9", "fields": {}}
+    ///     ]
+    /// }
+    /// ```
+    /// 没有记录的`kind`和`location`为`null`；[Errors]会额外包含`errors`，为每个错误的报告
+    fn report_json(&self) -> Value;
+}
+
+/// 错误链中一层的信息
+struct Cause<'a> {
+    message: String,
+    kind: Option<ErrorKind>,
+    location: Option<&'static Location<'static>>,
+    fields: &'a [(String, String)],
+    errors: Option<&'a Errors>,
+}
+
+impl<'a> Cause<'a> {
+    fn new(e: &'a (dyn Error + 'static)) -> Self {
+        let info = e.downcast_ref::<ErrInfo>();
+        let kind = match info {
+            Some(info) => Some(info.kind()),
+            None => e
+                .downcast_ref::<std::io::Error>()
+                .map(|e| ErrorKind::from(e.kind())),
+        };
+        Self {
+            message: e.to_string(),
+            kind,
+            location: info.and_then(|info| info.location()),
+            fields: info.map(|info| info.fields()).unwrap_or_default(),
+            errors: e.downcast_ref::<Errors>(),
+        }
+    }
+
+    /// 错误信息之后的类型和字段
+    fn suffix(&self) -> String {
+        let mut s = String::new();
+        if let Some(kind) = self.kind.filter(|k| *k != ErrorKind::Other) {
+            let _ = write!(s, " [{kind}]");
+        }
+        if !self.fields.is_empty() {
+            let fields: Vec<String> = self
+                .fields
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            let _ = write!(s, " {{{}}}", fields.join(", "));
+        }
+        s
+    }
+
+    fn json(&self) -> Value {
+        let fields: Map<String, Value> = self
+            .fields
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        let mut value = json!({
+            "message": self.message,
+            "kind": self.kind.map(|k| k.code()),
+            "location": self.location.map(|l| l.to_string()),
+            "fields": fields,
+        });
+        if let Some(errors) = self.errors {
+            value["errors"] = errors.iter().map(|e| e.report_json()).collect();
+        }
+        value
+    }
+}
+
+/// 多行的错误信息缩进对齐
+fn indent(s: &str, indent: &str) -> String {
+    s.lines().collect::<Vec<_>>().join(&format!("\n{indent}"))
+}
+
+fn render(err: &Err, color: bool) -> String {
+    let paint = |s: String, f: fn(&str) -> colored::ColoredString| {
+        if color { f(&s).to_string() } else { s }
+    };
+    let mut out = String::new();
+    for (i, cause) in err.chain().map(Cause::new).enumerate() {
+        let (head, pad) = if i == 0 {
+            (String::new(), "")
+        } else {
+            if i == 1 {
+                out.push_str(&paint("\nCaused by:".to_string(), |s| s.yellow()));
+            }
+            (format!("\n    {}: ", i - 1), "       ")
+        };
+        let message = indent(&cause.message, pad);
+        let message = if i == 0 {
+            paint(message, |s| s.red().bold())
+        } else {
+            paint(message, |s| s.red())
+        };
+        let _ = write!(out, "{head}{message}{}", cause.suffix());
+        if let Some(location) = cause.location {
+            let at = paint(format!("at {location}"), |s| s.dimmed());
+            let _ = write!(out, "\n{pad}    {at}");
+        }
+    }
+    let backtrace = err.backtrace();
+    if backtrace.status() == BacktraceStatus::Captured {
+        out.push_str(&paint("\nBacktrace:".to_string(), |s| s.yellow()));
+        let _ = write!(out, "\n{backtrace}");
+    }
+    out
+}
+
+impl ErrReportExt for Err {
+    fn report(&self) -> String {
+        render(self, false)
+    }
+
+    fn report_colored(&self) -> String {
+        render(self, true)
+    }
+
+    fn report_json(&self) -> Value {
+        let chain: Vec<Value> = self.chain().map(|e| Cause::new(e).json()).collect();
+        let fields: Map<String, Value> = self
+            .fields()
+            .into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect();
+        json!({
+            "message": self.to_string(),
+            "kind": self.kind().code(),
+            "location": self.location().map(|l| l.to_string()),
+            "fields": fields,
+            "chain": chain,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        newerr,
+        prelude::{ErrContextExt, ErrorsIterExt, Result},
+    };
+
+    fn load() -> Result<String> {
+        let s = std::fs::read_to_string("/not/exists/file")
+            .newerr_msg("load config")
+            .with_kv("path", "/not/exists/file")?;
+        Ok(s)
+    }
+
+    #[test]
+    fn test_report() {
+        let err = load().unwrap_err();
+        let report = err.report();
+        assert!(report.starts_with("load config [NOT_FOUND] {path=/not/exists/file}"));
+        assert!(report.contains("\nCaused by:\n    0: No such file"));
+        assert!(report.contains(&format!("    at {}", file!())));
+        assert!(err.report_colored().contains("load config"));
+
+        let json = err.report_json();
+        assert_eq!(json["message"], "load config");
+        assert_eq!(json["kind"], "NOT_FOUND");
+        assert_eq!(json["fields"]["path"], "/not/exists/file");
+        assert_eq!(json["chain"].as_array().unwrap().len(), 2);
+        assert_eq!(json["chain"][1]["location"], Value::Null);
+
+        let err = Err::from(
+            (0..3)
+                .try_all(|i| Err::<(), _>(newerr!("item {i}")))
+                .unwrap_err(),
+        );
+        assert!(err.report().starts_with("3 errors:\n  1. item 0"));
+        assert_eq!(
+            err.report_json()["chain"][0]["errors"][2]["message"],
+            "item 2"
+        );
+    }
+}
@@ -1,7 +1,126 @@
-use std::sync::LockResult;
+use std::{
+    fmt::{Debug, Display},
+    ops::{Deref, DerefMut},
+    panic::Location,
+    sync::LockResult,
+};
 
-/// 使用[anyhow::Error]作为错误类型方便转化
-pub type Err = anyhow::Error;
+use crate::prelude::{ErrInfo, ErrReportExt, ErrorKind};
+
+///
+/// 错误类型，包装[anyhow::Error]方便转化
+///
+/// 任意[std::error::Error]和[anyhow::Error]都可以通过`?`转为[Err]，
+/// 通过[Deref]可以使用[anyhow::Error]的方法，如`downcast_ref`、`chain`、`backtrace`
+///
+/// - `{}`只输出错误信息，`{:#}`输出整个错误链
+/// - `{:?}`输出[ErrReportExt::report]，包括每层的类型、字段、创建位置以及调用栈
+/// - `{:#?}`输出[anyhow::Error]的结构
+pub struct Err {
+    inner: anyhow::Error,
+}
+
+impl Err {
+    pub fn new<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::from(error)
+    }
+
+    /// 使用任意可输出的内容创建错误，见[anyhow::Error::msg]
+    pub fn msg<M>(message: M) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        Self::from(anyhow::Error::msg(message))
+    }
+
+    /// 以当前错误为原因，包装一层错误信息，见[anyhow::Error::context]
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        Self::from(self.inner.context(context))
+    }
+
+    /// 取出原类型的错误，见[anyhow::Error::downcast]
+    pub fn downcast<E>(self) -> std::result::Result<E, Self>
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        self.inner.downcast().map_err(Self::from)
+    }
+
+    pub fn into_inner(self) -> anyhow::Error {
+        self.inner
+    }
+}
+
+impl<E> From<E> for Err
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(error: E) -> Self {
+        Self {
+            inner: error.into(),
+        }
+    }
+}
+
+impl From<Err> for Box<dyn std::error::Error + Send + Sync + 'static> {
+    fn from(error: Err) -> Self {
+        error.inner.into()
+    }
+}
+
+impl From<Err> for Box<dyn std::error::Error + 'static> {
+    fn from(error: Err) -> Self {
+        error.inner.into()
+    }
+}
+
+impl Deref for Err {
+    type Target = anyhow::Error;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Err {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl AsRef<dyn std::error::Error + Send + Sync + 'static> for Err {
+    fn as_ref(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self.inner.as_ref()
+    }
+}
+
+impl AsRef<dyn std::error::Error + 'static> for Err {
+    fn as_ref(&self) -> &(dyn std::error::Error + 'static) {
+        self.inner.as_ref()
+    }
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+impl Debug for Err {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            Debug::fmt(&self.inner, f)
+        } else {
+            f.write_str(&self.report())
+        }
+    }
+}
 ///
 /// 使用[crate::prelude::Err]作为错误类型
 ///
@@ -12,32 +131,164 @@ pub type Result<T> = std::result::Result<T, Err>;
 ///
 /// 创建一个新的Err
 ///
+/// 会记录调用位置，通过[crate::prelude::ErrInfoExt::location]获取；
+/// 设置了`RUST_BACKTRACE`时会记录调用栈，通过[anyhow::Error::backtrace]获取；都会在`{:?}`中输出
+///
 /// 支持:
 /// ```ignore
 /// newerr!("{}, {}", a, b)
 /// newerr!("{a}")
 /// newerr!(Err::NewErr) => newerr!("{:?}", Err::NewErr)
 /// newerr!(a) => newerr!("{a:?}")
+///
+/// // 指定错误类型，见[crate::prelude::ErrorKind]
+/// newerr!(kind = NotFound, "user {id}")
+/// newerr!(kind = Internal, a)
 ///```
 ///
 #[macro_export]
 macro_rules! newerr {
+    (kind = $kind:ident, $fmt:literal) => {
+        $crate::prelude::Err::from($crate::prelude::ErrInfo::new(
+            $crate::prelude::ErrorKind::$kind,
+            format!($fmt),
+        ))
+    };
+    (kind = $kind:ident, $fmt:literal, $($arg:tt)*) => {
+        $crate::prelude::Err::from($crate::prelude::ErrInfo::new(
+            $crate::prelude::ErrorKind::$kind,
+            format!($fmt, $($arg)*),
+        ))
+    };
+    (kind = $kind:ident, $err:expr) => {
+        $crate::prelude::Err::from($crate::prelude::ErrInfo::new(
+            $crate::prelude::ErrorKind::$kind,
+            format!("{:?}", $err),
+        ))
+    };
     ($fmt:literal) => {
-        $crate::prelude::Err::msg(format!($fmt))
+        $crate::newerr!(kind = Other, $fmt)
     };
     ($fmt:literal, $($arg:tt)*) => {
-        $crate::prelude::Err::msg(format!($fmt, $($arg)*))
+        $crate::newerr!(kind = Other, $fmt, $($arg)*)
     };
     ($err:expr) => {
-        $crate::prelude::Err::msg(format!("{:?}", $err))
+        $crate::newerr!(kind = Other, $err)
+    };
+}
+
+///
+/// 返回[crate::newerr]创建的错误
+///
+/// 参数与[crate::newerr]相同
+///
+/// ```ignore
+/// bail!("user {id} not found")
+/// bail!(kind = NotFound, "user {id}")
+/// bail!(e)
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)+) => {
+        return ::core::result::Result::Err($crate::newerr!($($arg)+))
+    };
+}
+
+///
+/// 条件不满足时返回错误
+///
+/// 错误信息的参数与[crate::newerr]相同，省略时为条件本身
+///
+/// ```ignore
+/// ensure!(age >= 18)
+/// ensure!(age >= 18, "age {age} too young")
+/// ensure!(path.exists(), kind = NotFound, "{path:?}")
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            $crate::bail!("{}", concat!("condition failed: `", stringify!($cond), "`"));
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::bail!($($arg)+);
+        }
+    };
+}
+
+///
+/// 两个值不相等时返回错误
+///
+/// 错误信息的参数与[crate::newerr]相同，省略时输出两个值
+///
+/// ```ignore
+/// ensure_eq!(len, 4)
+/// ensure_eq!(len, 4, kind = InvalidInput, "bad header length {len}")
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::bail!(
+                        "{} != {}: left: {:?}, right: {:?}",
+                        stringify!($left),
+                        stringify!($right),
+                        left,
+                        right
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        if !($left == $right) {
+            $crate::bail!($($arg)+);
+        }
+    };
+}
+
+///
+/// 取出[Option]中的值，为[None]时返回错误
+///
+/// 错误信息的参数与[crate::newerr]相同，省略时为表达式本身
+///
+/// ```ignore
+/// let user = ensure_some!(users.get(&id));
+/// let user = ensure_some!(users.get(&id), kind = NotFound, "user {id}");
+/// ```
+#[macro_export]
+macro_rules! ensure_some {
+    ($opt:expr $(,)?) => {
+        match $opt {
+            ::core::option::Option::Some(v) => v,
+            ::core::option::Option::None => {
+                $crate::bail!("{}", concat!("`", stringify!($opt), "` is None"))
+            }
+        }
+    };
+    ($opt:expr, $($arg:tt)+) => {
+        match $opt {
+            ::core::option::Option::Some(v) => v,
+            ::core::option::Option::None => $crate::bail!($($arg)+),
+        }
     };
 }
 
 /// 错误类型转换，将不支持自动转换的常用错误类型手动转换
+///
+/// 转换后的错误会记录调用[ErrMapperExt::newerr]的位置
 pub trait ErrMapperExt<T> {
     fn newerr(self) -> Result<T>;
 }
 
+pub(crate) fn err_at(kind: ErrorKind, msg: String, location: &'static Location<'static>) -> Err {
+    ErrInfo::new(kind, msg).at(location).into()
+}
+
 impl<T> ErrMapperExt<T> for LockResult<T> {
     ///
     /// 将[std::sync::LockResult]转换为[Result]
@@ -51,31 +302,18 @@ impl<T> ErrMapperExt<T> for LockResult<T> {
     /// let result: Result<()> = mutex.lock().newerr();
     /// assert!(result.is_ok());
     /// ```
+    #[track_caller]
     fn newerr(self) -> Result<T> {
-        self.map_err(|e| newerr!(e))
-    }
-}
-
-impl<T> ErrMapperExt<T> for std::thread::Result<T> {
-    ///
-    /// 将[std::thread::Result]转换为[Result]
-    ///
-    /// # example
-    /// ```
-    /// use std::thread;
-    /// use libcommon::prelude::{Result, ErrMapperExt};
-    ///
-    /// let result: Result<()> = thread::spawn(|| ()).join().newerr();
-    /// assert!(result.is_ok());
-    /// ```
-    fn newerr(self) -> Result<T> {
-        self.map_err(|e| newerr!(e))
+        let location = Location::caller();
+        self.map_err(|e| err_at(ErrorKind::Other, format!("{e:?}"), location))
     }
 }
 
 impl<T> ErrMapperExt<T> for Option<T> {
+    #[track_caller]
     fn newerr(self) -> Result<T> {
-        self.ok_or_else(|| newerr!("err from None"))
+        let location = Location::caller();
+        self.ok_or_else(|| err_at(ErrorKind::Other, "err from None".to_string(), location))
     }
 }
 
@@ -120,6 +358,33 @@ mod tests {
         assert!(err.is_err());
     }
 
+    fn check(age: u32, name: Option<&str>) -> Result<&str> {
+        crate::ensure!(age > 0);
+        crate::ensure!(age < 150, kind = InvalidInput, "age {age} too old");
+        crate::ensure_eq!(age % 2, 0);
+        let name = crate::ensure_some!(name, kind = NotFound, "name of {age}");
+        if name.is_empty() {
+            crate::bail!(NewErr::NewErr);
+        }
+        Ok(name)
+    }
+
+    #[test]
+    fn test_ensure() {
+        use crate::prelude::{ErrInfoExt, ErrorKind};
+
+        assert_eq!(check(2, Some("a")).unwrap(), "a");
+        let err = check(0, None).unwrap_err();
+        assert_eq!(err.to_string(), "condition failed: `age > 0`");
+        assert!(err.location().is_some_and(|l| l.file() == file!()));
+        let err = check(200, None).unwrap_err();
+        assert!(err.is_kind(ErrorKind::InvalidInput));
+        let err = check(3, None).unwrap_err();
+        assert_eq!(err.to_string(), "age % 2 != 0: left: 1, right: 0");
+        assert!(check(2, None).unwrap_err().is_kind(ErrorKind::NotFound));
+        assert_eq!(check(2, Some("")).unwrap_err().to_string(), "NewErr");
+    }
+
     fn _convert_err_to_anyhow() -> Result<()> {
         Err(NewErr::NewErr)?
     }
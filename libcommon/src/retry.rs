@@ -0,0 +1,334 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use macro_builder::With;
+
+use crate::{
+    if_feature,
+    log::sample::random,
+    prelude::{Err, ErrInfo, ErrInfoExt, Errors, Result},
+};
+
+/// 判断错误是否需要重试
+type Retryable = Arc<dyn Fn(&Err) -> bool + Send + Sync>;
+
+/// 重试前的等待时间
+#[derive(Debug, Clone, Copy)]
+pub enum Backoff {
+    /// 每次等待相同时间
+    Fixed(Duration),
+    /// 从`initial`开始每次翻倍，最长`max`
+    Exponential { initial: Duration, max: Duration },
+    /// 与[Backoff::Exponential]相同，但实际等待`0..=当前等待时间`中的随机值，避免多个调用方同时重试
+    Jitter { initial: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// 第`attempt`次(从1开始)失败后的等待时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = |initial: Duration, max: Duration| {
+            initial
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+                .min(max)
+        };
+        match *self {
+            Backoff::Fixed(d) => d,
+            Backoff::Exponential { initial, max } => exp(initial, max),
+            Backoff::Jitter { initial, max } => exp(initial, max).mul_f64(random()),
+        }
+    }
+}
+
+///
+/// 重试策略，见[retry]
+///
+/// 默认最多执行3次，每次间隔100ms，所有错误都会重试
+///
+/// # example
+///
+/// ```ignore
+/// let policy = RetryPolicy::exponential(Duration::from_millis(100))
+///     .with_times(5)
+///     .with_deadline(Duration::from_secs(10))
+///     .retry_if(|e| e.is_kind(ErrorKind::Timeout) || e.is_kind(ErrorKind::Unavailable));
+/// let body = retry(&policy, || fetch(url))?;
+/// ```
+#[derive(With, Clone)]
+pub struct RetryPolicy {
+    backoff: Backoff,
+    /**
+     * 最多执行的次数，包括第一次
+     */
+    times: u32,
+    /**
+     * 从第一次执行开始计算的最长时间，超过后不再重试
+     */
+    deadline: Option<Duration>,
+    /**
+     * 日志中显示的名称
+     */
+    name: Option<String>,
+    #[with(skip)]
+    retryable: Option<Retryable>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::fixed(Duration::from_millis(100))
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(backoff: Backoff) -> Self {
+        Self {
+            backoff,
+            times: 3,
+            deadline: None,
+            name: None,
+            retryable: None,
+        }
+    }
+
+    pub fn fixed(delay: Duration) -> Self {
+        Self::new(Backoff::Fixed(delay))
+    }
+
+    /// 最长等待时间默认为30s
+    pub fn exponential(initial: Duration) -> Self {
+        Self::new(Backoff::Exponential {
+            initial,
+            max: Duration::from_secs(30),
+        })
+    }
+
+    /// 最长等待时间默认为30s
+    pub fn jitter(initial: Duration) -> Self {
+        Self::new(Backoff::Jitter {
+            initial,
+            max: Duration::from_secs(30),
+        })
+    }
+
+    /// 只重试满足条件的错误，其它错误直接返回
+    pub fn retry_if(mut self, f: impl Fn(&Err) -> bool + Send + Sync + 'static) -> Self {
+        self.retryable = Some(Arc::new(f));
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("retry")
+    }
+
+    /// 失败后是否继续重试，返回等待时间
+    fn next_delay(&self, attempt: u32, start: Instant, err: &Err) -> Option<Duration> {
+        if attempt >= self.times || self.retryable.as_ref().is_some_and(|f| !f(err)) {
+            return None;
+        }
+        let delay = self.backoff.delay(attempt);
+        match self.deadline {
+            Some(deadline) if start.elapsed() + delay > deadline => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+/// 重试的状态，记录每次的错误
+struct Attempts<'a> {
+    policy: &'a RetryPolicy,
+    start: Instant,
+    errors: Errors,
+}
+
+impl<'a> Attempts<'a> {
+    fn new(policy: &'a RetryPolicy) -> Self {
+        Self {
+            policy,
+            start: Instant::now(),
+            errors: Errors::new(),
+        }
+    }
+
+    /// 记录失败，需要重试时返回等待时间，否则返回最终的错误
+    fn failed(&mut self, err: Err) -> std::result::Result<Duration, Err> {
+        let attempt = self.errors.len() as u32 + 1;
+        let name = self.policy.name();
+        let times = self.policy.times;
+        let delay = self.policy.next_delay(attempt, self.start, &err);
+        match delay {
+            Some(delay) => {
+                warn!("{name}: attempt {attempt}/{times} failed: {err:#}, retry in {delay:?}");
+                self.errors.push(err);
+                Ok(delay)
+            }
+            None => {
+                warn!("{name}: attempt {attempt}/{times} failed: {err:#}, giving up");
+                if self.errors.is_empty() {
+                    return Err(err);
+                }
+                let kind = err.kind();
+                self.errors.push(err);
+                let errors = std::mem::take(&mut self.errors);
+                let msg = format!("{name} failed after {attempt} attempts");
+                Err(ErrInfo::wrap(errors.into(), msg).with_kind(kind).into())
+            }
+        }
+    }
+}
+
+///
+/// 执行`op`，失败时按策略重试
+///
+/// 每次失败都会输出`warn`日志；重试多次后仍失败时，返回的错误包含每次的错误([Errors])，
+/// 错误类型([crate::prelude::ErrorKind])与最后一次相同；只执行了一次时返回原错误
+///
+/// # example
+///
+/// ```
+/// use std::time::Duration;
+/// use libcommon::{newerr, retry::{RetryPolicy, retry}};
+///
+/// let policy = RetryPolicy::fixed(Duration::from_millis(1));
+/// let mut n = 0;
+/// let value = retry(&policy, || {
+///     n += 1;
+///     if n < 3 { Err(newerr!("attempt {n}")) } else { Ok(n) }
+/// });
+/// assert_eq!(value.unwrap(), 3);
+/// ```
+pub fn retry<T, F>(policy: &RetryPolicy, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut attempts = Attempts::new(policy);
+    loop {
+        match op() {
+            Ok(v) => return Ok(v),
+            Err(e) => std::thread::sleep(attempts.failed(e)?),
+        }
+    }
+}
+
+if_feature!("tokio" =>
+    ///
+    /// [retry]的异步版本，使用[tokio::time::sleep]等待
+    pub async fn retry_async<T, F, Fut>(policy: &RetryPolicy, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempts = Attempts::new(policy);
+        loop {
+            match op().await {
+                Ok(v) => return Ok(v),
+                Err(e) => tokio::time::sleep(attempts.failed(e)?).await,
+            }
+        }
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{newerr, prelude::ErrorKind, retry};
+
+    fn fast() -> RetryPolicy {
+        RetryPolicy::fixed(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut n = 0;
+        let err = retry(&fast(), || -> Result<()> {
+            n += 1;
+            Err(newerr!(kind = Timeout, "timeout {n}"))
+        })
+        .unwrap_err();
+        assert_eq!(n, 3);
+        assert!(err.is_kind(ErrorKind::Timeout));
+        assert_eq!(err.to_string(), "retry failed after 3 attempts");
+        let errors = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<Errors>())
+            .unwrap();
+        assert_eq!(errors.len(), 3);
+
+        let mut n = 0;
+        let policy = fast().retry_if(|e| e.is_kind(ErrorKind::Timeout));
+        let err = retry(&policy, || -> Result<()> {
+            n += 1;
+            Err(newerr!(kind = NotFound, "not found"))
+        })
+        .unwrap_err();
+        assert_eq!(n, 1);
+        assert_eq!(err.to_string(), "not found");
+
+        let mut n = 0;
+        let policy = fast()
+            .with_times(10)
+            .with_deadline(Duration::from_millis(20));
+        let policy = policy.with_backoff(Backoff::Fixed(Duration::from_millis(8)));
+        let _ = retry(&policy, || -> Result<()> {
+            n += 1;
+            Err(newerr!("fail"))
+        });
+        assert!(n < 10);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let exp = Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+        };
+        assert_eq!(exp.delay(1), Duration::from_millis(100));
+        assert_eq!(exp.delay(3), Duration::from_millis(400));
+        assert_eq!(exp.delay(10), Duration::from_millis(500));
+        let jitter = Backoff::Jitter {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+        };
+        assert!(jitter.delay(2) <= Duration::from_millis(200));
+    }
+
+    #[retry(times = 2, backoff = "exp", delay = 1)]
+    fn flaky(n: &mut u32) -> Result<u32> {
+        *n += 1;
+        if *n < 2 {
+            return Err(newerr!("flaky"));
+        }
+        Ok(*n)
+    }
+
+    #[test]
+    fn test_macro() {
+        let mut n = 0;
+        assert_eq!(flaky(&mut n).unwrap(), 2);
+    }
+
+    if_feature!("tokio" =>
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        #[retry(times = 3, backoff = "jitter", delay = 1)]
+        async fn flaky_async(n: &AtomicU32) -> Result<u32> {
+            let n = n.fetch_add(1, Ordering::Relaxed) + 1;
+            if n < 3 { Err(newerr!("attempt {n}")) } else { Ok(n) }
+        }
+
+        #[test]
+        fn test_retry_async() {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let n = AtomicU32::new(0);
+            let value = rt.block_on(retry_async(&fast(), || async {
+                let n = n.fetch_add(1, Ordering::Relaxed) + 1;
+                if n < 3 { Err(newerr!("attempt {n}")) } else { Ok(n) }
+            }));
+            assert_eq!(value.unwrap(), 3);
+
+            let n = AtomicU32::new(0);
+            assert_eq!(rt.block_on(flaky_async(&n)).unwrap(), 3);
+        }
+    );
+}